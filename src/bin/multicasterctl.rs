@@ -0,0 +1,142 @@
+use clap::{Parser, Subcommand};
+use multicaster::control::{self, Request, Response};
+use std::{path::PathBuf, process};
+
+/// Control a running multicaster through its control socket
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Path of the control socket, `[control] socket` in the daemon's config
+    #[arg(short, long, default_value = "/run/multicaster.sock")]
+    socket: PathBuf,
+
+    /// Print the raw JSON response
+    #[arg(long)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the rules and the interfaces they resolve to
+    Rules,
+    /// Show packets forwarded and dropped per rule
+    Counters,
+    /// Show the names of recently forwarded packets
+    Recent,
+    /// Re-read the daemon's config file
+    Reload,
+    /// Turn a rule on again
+    Enable { rule: usize },
+    /// Turn a rule off until it is enabled again or the config is reloaded
+    Disable { rule: usize },
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let request = match cli.command {
+        Command::Rules => Request::Rules,
+        Command::Counters => Request::Counters,
+        Command::Recent => Request::Recent,
+        Command::Reload => Request::Reload,
+        Command::Enable { rule } => Request::Enable { rule },
+        Command::Disable { rule } => Request::Disable { rule },
+    };
+
+    let response = match control::request(&cli.socket, &request) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("error in talking to {}: {}", cli.socket.display(), e);
+            process::exit(1);
+        }
+    };
+
+    if cli.json {
+        println!(
+            "{}",
+            serde_json::to_string(&response).expect("error in serializing response")
+        );
+    } else {
+        print(&response);
+    }
+
+    if let Response::Error(_) = response {
+        process::exit(1);
+    }
+}
+
+fn print(response: &Response) {
+    match response {
+        Response::Rules(rules) => {
+            for r in rules {
+                let state = match (r.enabled, r.active) {
+                    (false, _) => "disabled",
+                    (true, false) => "outside schedule",
+                    (true, true) => "enabled",
+                };
+
+                println!("rule {} ({})", r.rule, state);
+                println!(
+                    "  sources       {} -> {}",
+                    r.sources.join(", "),
+                    list(&r.source_interfaces)
+                );
+                println!(
+                    "  destinations  {} -> {}",
+                    r.destinations.join(", "),
+                    list(&r.destination_interfaces)
+                );
+                if !r.filters.is_empty() {
+                    println!("  filters       {}", r.filters.join(", "));
+                }
+                if !r.deny.is_empty() {
+                    println!("  deny          {}", r.deny.join(", "));
+                }
+            }
+        }
+
+        Response::Counters(counters) => {
+            for c in counters {
+                let dropped: Vec<_> = c
+                    .dropped
+                    .iter()
+                    .map(|(reason, n)| format!("{} {}", n, reason))
+                    .collect();
+
+                println!(
+                    "rule {}: {} forwarded, dropped: {}",
+                    c.rule,
+                    c.forwarded,
+                    list(&dropped)
+                );
+            }
+        }
+
+        Response::Recent(forwarded) => {
+            for f in forwarded {
+                println!(
+                    "{} rule {} {} -> {}: {}",
+                    f.time,
+                    f.rule,
+                    f.from,
+                    f.to.join(","),
+                    f.names.join(" ")
+                );
+            }
+        }
+
+        Response::Done => println!("ok"),
+        Response::Error(e) => eprintln!("{}", e),
+    }
+}
+
+fn list(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
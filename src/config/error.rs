@@ -0,0 +1,85 @@
+use super::Source;
+use std::{fmt, io, ops::Range};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("error in reading {path}: {source}")]
+    Read { path: String, source: io::Error },
+
+    #[error("{0}")]
+    Parse(Diagnostic),
+
+    /// Every finding of the validation pass, warnings included. At least one is an error.
+    #[error("{}", .0.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("\n"))]
+    Invalid(Vec<Diagnostic>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The config works but probably doesn't do what was intended
+    Warning,
+    /// The config is rejected
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in the config. `span` is a byte range of the TOML source and `line` starts
+/// at 1. Both are `None` for configs that weren't parsed from TOML. `file` is the config file or
+/// included fragment the problem is in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<String>,
+    pub span: Option<Range<usize>>,
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    pub(super) fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            message,
+            file: None,
+            span: None,
+            line: None,
+        }
+    }
+
+    /// Points the diagnostic at `span` of `source`
+    pub(super) fn at(mut self, source: &Source, span: Option<Range<usize>>) -> Self {
+        let contents = &source.contents;
+        self.file = source.file.clone();
+        self.line = span.as_ref().map(|span| {
+            contents[..span.start.min(contents.len())]
+                .matches('\n')
+                .count()
+                + 1
+        });
+        self.span = span;
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file)?;
+        }
+
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
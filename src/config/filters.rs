@@ -0,0 +1,136 @@
+// Allow (`filters`) and deny (`deny`) entries of a rule. An entry is a name or a glob such as
+// `*._airplay._tcp.local`. When an allow and a deny entry both match a name the more specific one
+// wins: an exact name beats a glob and a glob with more literal characters beats one with fewer.
+// A tie goes to the deny entry. Names are compared ignoring ASCII case, like DNS does.
+use super::{glob_match, is_glob, MdnsConfig};
+use std::{collections::HashSet, fmt};
+
+/// How a rule decided a packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Allowed by this entry, or by the rule having no allow entries if `None`
+    Allow(Option<String>),
+    /// Denied by this entry
+    Deny(String),
+    /// Dropped because the sender isn't in `allowed_sources` or `allowed_macs`
+    SenderNotAllowed,
+    /// Dropped because none of the rule's `schedule` windows is open
+    OutsideSchedule,
+}
+
+impl Verdict {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Verdict::Allow(_))
+    }
+
+    /// Short name of the outcome without the entry, e.g. for grouping decisions in metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Verdict::Allow(_) => "allowed",
+            Verdict::Deny(_) => "denied",
+            Verdict::SenderNotAllowed => "sender_not_allowed",
+            Verdict::OutsideSchedule => "outside_schedule",
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Allow(Some(entry)) => write!(f, "allowed by {:?}", entry),
+            Verdict::Allow(None) => write!(f, "allowed, rule has no filters"),
+            Verdict::Deny(entry) => write!(f, "denied by {:?}", entry),
+            Verdict::SenderNotAllowed => write!(f, "sender not allowed"),
+            Verdict::OutsideSchedule => write!(f, "outside schedule"),
+        }
+    }
+}
+
+impl MdnsConfig {
+    /// Decides a single name. Returns `None` if no entry matches it.
+    pub fn check_name(&self, name: &str) -> Option<Verdict> {
+        let name = name.to_ascii_lowercase();
+        let best = |entries: &HashSet<String>| {
+            entries
+                .iter()
+                .filter(|entry| glob_match(&entry.to_ascii_lowercase(), &name))
+                .max_by_key(|entry| specificity(entry))
+                .cloned()
+        };
+
+        match (best(&self.filters), best(&self.deny)) {
+            (Some(allow), Some(deny)) if specificity(&allow) > specificity(&deny) => {
+                Some(Verdict::Allow(Some(allow)))
+            }
+            (_, Some(deny)) => Some(Verdict::Deny(deny)),
+            (Some(allow), None) => Some(Verdict::Allow(Some(allow))),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the deny entry that wins for any of `names`. Packets are forwarded whole, so one
+    /// denied name is enough to keep the packet out of this rule.
+    pub fn denied<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Option<Verdict> {
+        names
+            .into_iter()
+            .filter_map(|name| self.check_name(name))
+            .find(|verdict| matches!(verdict, Verdict::Deny(_)))
+    }
+}
+
+/// Exact names sort above every glob, globs by their number of literal characters
+fn specificity(entry: &str) -> (bool, usize) {
+    let literal = entry.chars().filter(|c| !matches!(c, '*' | '?')).count();
+    (!is_glob(entry), literal)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rule(filters: &[&str], deny: &[&str]) -> MdnsConfig {
+        toml::from_str(&format!(
+            "sources = [\"eth0\"]\ndestinations = [\"eth1\"]\nfilters = {:?}\ndeny = {:?}",
+            filters, deny
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn most_specific_entry_wins() {
+        let conf = rule(
+            &["*._airplay._tcp.local", "_airplay._tcp.local"],
+            &["Living Room TV._airplay._tcp.local"],
+        );
+
+        assert_eq!(
+            conf.check_name("Kitchen._airplay._tcp.local"),
+            Some(Verdict::Allow(Some("*._airplay._tcp.local".to_string())))
+        );
+        assert_eq!(
+            conf.check_name("Living Room TV._airplay._tcp.local"),
+            Some(Verdict::Deny(
+                "Living Room TV._airplay._tcp.local".to_string()
+            ))
+        );
+        assert_eq!(conf.check_name("_ssh._tcp.local"), None);
+
+        let conf = rule(&["nas._smb._tcp.local"], &["*._smb._tcp.local"]);
+        assert_eq!(
+            conf.check_name("nas._smb._tcp.local"),
+            Some(Verdict::Allow(Some("nas._smb._tcp.local".to_string())))
+        );
+
+        let conf = rule(&["_ipp._tcp.local"], &["_IPP._tcp.local"]);
+        assert_eq!(
+            conf.check_name("_Ipp._TCP.local"),
+            Some(Verdict::Deny("_IPP._tcp.local".to_string()))
+        );
+
+        let conf = rule(&["*.local"], &["*.local"]);
+        assert_eq!(
+            conf.check_name("printer.local"),
+            Some(Verdict::Deny("*.local".to_string()))
+        );
+    }
+}
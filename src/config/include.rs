@@ -0,0 +1,196 @@
+// Fragments listed in `include` are merged into the config that includes them. Their rules are
+// appended in file order. A zone or filter set may be defined in more than one file as long as
+// every definition is the same.
+use super::{
+    glob_match, is_glob, validate::Location, Config, ConfigError, Diagnostic, FilterSet,
+    MdnsConfig, Severity,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// The parts of a config that can come from an included file. Socket settings and further
+/// includes are only allowed in the main file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct Fragment {
+    #[serde(default)]
+    mdns: Vec<MdnsConfig>,
+    #[serde(default)]
+    zones: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    filter_sets: HashMap<String, FilterSet>,
+}
+
+/// Files matched by the `include` patterns, relative to `base`. Only the file name part of a
+/// pattern may be a glob. Matches are sorted so rules are merged in a stable order.
+pub(super) fn files(base: &Path, patterns: &[String]) -> Result<Vec<PathBuf>, ConfigError> {
+    let mut out = vec![];
+
+    for pattern in patterns {
+        let path = base.join(pattern);
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        if !is_glob(&name) {
+            out.push(path);
+            continue;
+        }
+
+        let dir = path.parent().unwrap_or(base);
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(source) => {
+                return Err(ConfigError::Read {
+                    path: dir.display().to_string(),
+                    source,
+                })
+            }
+        };
+
+        let mut matches: Vec<_> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|p| p.is_file())
+            .filter(|p| {
+                p.file_name()
+                    .is_some_and(|n| glob_match(&name, &n.to_string_lossy()))
+            })
+            .collect();
+        matches.sort();
+
+        out.extend(matches);
+    }
+
+    Ok(out)
+}
+
+impl Config {
+    /// Merges a fragment read from the source at `index`. Returns a finding for every zone or
+    /// filter set that conflicts with an earlier definition.
+    pub(super) fn merge(
+        &mut self,
+        fragment: Fragment,
+        index: usize,
+    ) -> Vec<(usize, Location, Diagnostic)> {
+        let mut conflicts = vec![];
+        let mut conflict = |location, message| {
+            conflicts.push((index, location, Diagnostic::new(Severity::Error, message)))
+        };
+
+        self.mdns.extend(fragment.mdns);
+
+        for (name, members) in fragment.zones {
+            match self.zones.get(&name) {
+                Some(existing) if *existing != members => conflict(
+                    Location::Zone(name.clone()),
+                    format!("zone {} is already defined with other interfaces", name),
+                ),
+                Some(_) => {}
+                None => {
+                    self.zones.insert(name, members);
+                }
+            }
+        }
+
+        for (name, set) in fragment.filter_sets {
+            match self.filter_sets.get(&name) {
+                Some(existing) if *existing != set => conflict(
+                    Location::FilterSet(name.clone()),
+                    format!("filter set {} is already defined with other filters", name),
+                ),
+                Some(_) => {}
+                None => {
+                    self.filter_sets.insert(name, set);
+                }
+            }
+        }
+
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_includes_and_detect_conflicts() {
+        let dir = std::env::temp_dir().join(format!("multicaster-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+
+        fs::write(
+            dir.join("config.toml"),
+            r#"
+include = ["conf.d/*.toml"]
+
+[filter_sets.printers]
+filters = ["_ipp._tcp.local"]
+
+[[mdns]]
+sources = ["eth0"]
+destinations = ["eth1"]
+filters = ["cups.local"]
+filter_sets = ["printers"]
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("conf.d/10-scanners.toml"),
+            r#"
+[filter_sets.printers]
+filters = ["_ipp._tcp.local"]
+
+[[mdns]]
+sources = ["eth2"]
+destinations = ["eth1"]
+filter_sets = ["printers"]
+"#,
+        )
+        .unwrap();
+        fs::write(dir.join("conf.d/README"), "not a config").unwrap();
+
+        let path = dir.join("config.toml");
+        let (config, _) = Config::load(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(config.mdns.len(), 2);
+        assert_eq!(
+            config.mdns[0].filters,
+            ["cups.local", "_ipp._tcp.local"].map(String::from).into()
+        );
+        assert_eq!(
+            config.mdns[1].filters,
+            ["_ipp._tcp.local"].map(String::from).into()
+        );
+
+        fs::write(
+            dir.join("conf.d/20-other.toml"),
+            "\n[filter_sets.printers]\nfilters = [\"_ipps._tcp.local\"]\n",
+        )
+        .unwrap();
+
+        let Err(ConfigError::Invalid(diagnostics)) = Config::load(path.to_str().unwrap()) else {
+            panic!("conflicting filter set was accepted");
+        };
+        let conflict = diagnostics
+            .iter()
+            .find(|d| d.severity == Severity::Error)
+            .unwrap();
+
+        assert_eq!(
+            conflict.message,
+            "filter set printers is already defined with other filters"
+        );
+        assert!(conflict.file.as_ref().unwrap().ends_with("20-other.toml"));
+        assert_eq!(conflict.line, Some(2));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
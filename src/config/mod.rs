@@ -0,0 +1,272 @@
+use crate::interfaces::InterfaceTable;
+use crate::socket::Backend;
+use ipnet::IpNet;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+mod error;
+mod filters;
+mod include;
+mod schedule;
+mod sources;
+mod validate;
+mod zones;
+
+pub use error::*;
+pub use filters::Verdict;
+pub use schedule::{LocalTime, Window};
+pub use sources::MacAddress;
+pub use zones::{glob_match, is_glob};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Files whose rules, zones and filter sets are merged into this config, e.g.
+    /// `include = ["conf.d/*.toml"]`. Paths are relative to the directory of this file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+
+    #[serde(default)]
+    pub mdns: Vec<MdnsConfig>,
+
+    /// Named groups of interfaces, e.g. `iot = ["eth70", "vlan7*"]`. Sources and destinations
+    /// can name a zone instead of listing its interfaces.
+    #[serde(default)]
+    pub zones: HashMap<String, Vec<String>>,
+
+    /// Named lists of filters that rules can use, e.g. `[filter_sets.printers]`
+    #[serde(default)]
+    pub filter_sets: HashMap<String, FilterSet>,
+
+    #[serde(default)]
+    pub sockets: SocketsConfig,
+
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    #[serde(default)]
+    pub control: ControlConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterSet {
+    pub filters: HashSet<String>,
+}
+
+/// Prometheus endpoint, e.g. `[metrics] listen = "127.0.0.1:9353"`. Disabled unless `listen`
+/// is set.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    pub listen: Option<SocketAddr>,
+}
+
+/// Control API used by `multicasterctl`, e.g. `[control] socket = "/run/multicaster.sock"`.
+/// Disabled unless `socket` is set.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlConfig {
+    pub socket: Option<PathBuf>,
+}
+
+/// Socket settings of each protocol, e.g. `[sockets.mdns]`
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SocketsConfig {
+    #[serde(default)]
+    pub mdns: SocketConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SocketConfig {
+    /// Socket backend, e.g. `backend = "bind_to_device"`
+    #[serde(default)]
+    pub backend: Backend,
+
+    /// IP TTL (hop limit) of forwarded packets. mDNS requires 255. RFC 6762 Section 11
+    #[serde(default = "SocketConfig::default_multicast_ttl")]
+    pub multicast_ttl: u8,
+
+    /// Drop received packets whose IP TTL is not 255. Such packets were routed from another
+    /// link or spoofed from off-link. RFC 6762 Section 11
+    #[serde(default)]
+    pub validate_ttl: bool,
+}
+
+impl SocketConfig {
+    fn default_multicast_ttl() -> u8 {
+        255
+    }
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        Self {
+            backend: Backend::default(),
+            multicast_ttl: Self::default_multicast_ttl(),
+            validate_ttl: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MdnsConfig {
+    pub destinations: Vec<String>,
+    pub sources: Vec<String>,
+    /// Names to forward, exact or globs such as `*._airplay._tcp.local`. An empty list
+    /// forwards everything that isn't denied.
+    #[serde(default)]
+    pub filters: HashSet<String>,
+
+    /// Names not to forward, e.g. `deny = ["Living Room TV._airplay._tcp.local"]`. When an
+    /// allow and a deny entry both match, the more specific one wins.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub deny: HashSet<String>,
+
+    /// Networks that packets arriving on a source interface must come from, e.g.
+    /// `allowed_sources = ["192.0.2.10/32", "fd00:1::/64"]`. Empty allows any sender. Only the
+    /// packet backend receives over IPv6, so IPv6 networks are rejected with any other backend.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_sources: Vec<IpNet>,
+
+    /// MAC addresses that packets arriving on a source interface must come from. Only the
+    /// packet backend reports MACs, so this is rejected with any other backend.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_macs: Vec<MacAddress>,
+
+    /// Names of filter sets whose filters are added to `filters` when the config is loaded
+    #[serde(default)]
+    pub filter_sets: Vec<String>,
+
+    /// DNS-SD TXT attributes every service instance in a response must carry in its own TXT
+    /// record to be forwarded, e.g. `pdl = "application/pdf"`. A value matches if it is one of
+    /// the comma separated values of the attribute. An empty value only checks that the key is
+    /// present. Responses without a service instance, e.g. host addresses, aren't checked.
+    #[serde(default)]
+    pub txt_filters: HashMap<String, String>,
+
+    /// Local time windows in which the rule forwards, e.g. `schedule = ["mon-fri 08:00-18:00"]`.
+    /// Empty means always.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<Window>,
+
+    /// Send goodbye packets for the PTR records forwarded during a window when it closes, so
+    /// clients on the destinations drop the services from their caches. RFC 6762 Section 10.1
+    #[serde(default)]
+    pub send_goodbyes: bool,
+
+    /// Remove EDNS0 options (e.g. the Owner option used by Sleep Proxy clients) from packets
+    /// forwarded by this rule. They describe the host's own link and are meaningless elsewhere.
+    #[serde(default)]
+    pub strip_edns_options: bool,
+}
+
+impl Config {
+    const FILENAME: &'static str = "config.toml";
+
+    /// Reads and validates the config. Warnings are logged, errors reject the config.
+    pub fn parse(filename: &str) -> Result<Config, ConfigError> {
+        let (config, warnings) = Config::load(filename)?;
+
+        for warning in warnings {
+            warn!("{}", warning);
+        }
+
+        Ok(config)
+    }
+
+    /// Reads and validates the config and the files it includes. Returns the warnings along
+    /// with the config.
+    pub fn load(mut filename: &str) -> Result<(Config, Vec<Diagnostic>), ConfigError> {
+        if filename.is_empty() {
+            debug!(
+                "filename is empty. using default name: {}",
+                Config::FILENAME
+            );
+            filename = Config::FILENAME;
+        }
+
+        let base = Path::new(filename).parent().unwrap_or(Path::new(""));
+        Config::from_source(Source::read(Path::new(filename))?, base)
+    }
+
+    /// Parses and validates a config. Includes are relative to the working directory.
+    pub fn from_toml(contents: &str) -> Result<(Config, Vec<Diagnostic>), ConfigError> {
+        let source = Source {
+            file: None,
+            contents: contents.to_string(),
+        };
+
+        Config::from_source(source, Path::new(""))
+    }
+
+    /// Interface names are checked against the interfaces that exist right now, a missing one
+    /// is only a warning since it may appear later.
+    fn from_source(source: Source, base: &Path) -> Result<(Config, Vec<Diagnostic>), ConfigError> {
+        let mut config: Config = source.parse()?;
+        let mut sources = vec![source];
+        let mut conflicts = vec![];
+
+        for path in include::files(base, &config.include)? {
+            let source = Source::read(&path)?;
+            conflicts.extend(config.merge(source.parse()?, sources.len()));
+            sources.push(source);
+        }
+
+        let interfaces = InterfaceTable::load()
+            .map_err(|e| debug!("error in loading interface table {:?}", e))
+            .ok();
+        let diagnostics = config.validate_sources(&sources, conflicts, interfaces.as_ref());
+
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(ConfigError::Invalid(diagnostics));
+        }
+
+        config.expand_filter_sets();
+
+        Ok((config, diagnostics))
+    }
+
+    /// Adds the filters of the filter sets each rule names to its own filters
+    pub fn expand_filter_sets(&mut self) {
+        for rule in &mut self.mdns {
+            for name in &rule.filter_sets {
+                if let Some(set) = self.filter_sets.get(name) {
+                    rule.filters.extend(set.filters.iter().cloned());
+                }
+            }
+        }
+    }
+}
+
+/// TOML text of a config file or fragment. `file` is `None` if it didn't come from a file.
+#[derive(Debug)]
+struct Source {
+    file: Option<String>,
+    contents: String,
+}
+
+impl Source {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        let file = path.display().to_string();
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: file.clone(),
+            source,
+        })?;
+
+        Ok(Self {
+            file: Some(file),
+            contents,
+        })
+    }
+
+    fn parse<T: serde::de::DeserializeOwned>(&self) -> Result<T, ConfigError> {
+        toml::from_str(&self.contents).map_err(|e| {
+            ConfigError::Parse(
+                Diagnostic::new(Severity::Error, e.message().to_string()).at(self, e.span()),
+            )
+        })
+    }
+}
@@ -0,0 +1,208 @@
+// Time windows of a rule's `schedule`, e.g. `"mon-fri 08:00-18:00"`. The days are optional and
+// default to every day. A window whose end is before its start runs past midnight into the next
+// day. Times are local time.
+use super::MdnsConfig;
+use serde::{Deserialize, Serialize};
+use std::{fmt, mem, str::FromStr};
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Weekday and time of day in local time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    /// 0 is Monday
+    pub weekday: u8,
+    /// Minutes since midnight
+    pub minute: u16,
+}
+
+impl LocalTime {
+    pub fn now() -> Self {
+        let now = unsafe { libc::time(std::ptr::null_mut()) };
+        let mut tm: libc::tm = unsafe { mem::zeroed() };
+        unsafe { libc::localtime_r(&now, &mut tm) };
+
+        Self {
+            weekday: ((tm.tm_wday + 6) % 7) as u8,
+            minute: (tm.tm_hour * 60 + tm.tm_min) as u16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Window {
+    /// Bit 0 is Monday
+    days: u8,
+    start: u16,
+    end: u16,
+}
+
+impl Window {
+    pub fn contains(&self, time: LocalTime) -> bool {
+        let on = |day: u8| self.days & (1 << day) != 0;
+
+        if self.start < self.end {
+            on(time.weekday) && (self.start..self.end).contains(&time.minute)
+        } else {
+            (on(time.weekday) && time.minute >= self.start)
+                || (on((time.weekday + 6) % 7) && time.minute < self.end)
+        }
+    }
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (days, times) = match s.trim().rsplit_once(' ') {
+            Some((days, times)) => (parse_days(days.trim())?, times),
+            None => (0x7f, s.trim()),
+        };
+
+        let (start, end) = times
+            .split_once('-')
+            .ok_or_else(|| format!("expected a time range such as 08:00-18:00, got {:?}", s))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+
+        if start == end {
+            return Err(format!("time range of {:?} is empty", s));
+        }
+
+        Ok(Self { days, start, end })
+    }
+}
+
+/// Comma separated days or ranges of days, e.g. `mon-fri,sun`. `*` is every day.
+fn parse_days(s: &str) -> Result<u8, String> {
+    if s == "*" {
+        return Ok(0x7f);
+    }
+
+    let day = |name: &str| {
+        DAYS.iter()
+            .position(|d| d.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| format!("unknown day {:?}, use one of {}", name, DAYS.join(", ")))
+    };
+
+    let mut days = 0;
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (mut d, last) = (day(first)?, day(last)?);
+                // Ranges may wrap around the week, e.g. fri-mon
+                loop {
+                    days |= 1 << d;
+                    if d == last {
+                        break;
+                    }
+                    d = (d + 1) % 7;
+                }
+            }
+            None => days |= 1 << day(part)?,
+        }
+    }
+
+    Ok(days)
+}
+
+/// `HH:MM` as minutes since midnight. `24:00` is the end of the day.
+fn parse_time(s: &str) -> Result<u16, String> {
+    let invalid = || format!("invalid time {:?}, expected HH:MM", s);
+    let (hours, minutes) = s.trim().split_once(':').ok_or_else(invalid)?;
+    let hours: u16 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+
+    if minutes >= 60 || hours > 24 || hours * 60 + minutes > 24 * 60 {
+        return Err(invalid());
+    }
+
+    Ok(hours * 60 + minutes)
+}
+
+impl TryFrom<String> for Window {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Window> for String {
+    fn from(window: Window) -> Self {
+        window.to_string()
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.days != 0x7f {
+            let days: Vec<_> = (0..7)
+                .filter(|d| self.days & (1 << d) != 0)
+                .map(|d| DAYS[d])
+                .collect();
+            write!(f, "{} ", days.join(","))?;
+        }
+
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+impl MdnsConfig {
+    /// Returns true if the rule has no schedule or one of its windows is open
+    pub fn is_active(&self, time: LocalTime) -> bool {
+        self.schedule.is_empty() || self.schedule.iter().any(|w| w.contains(time))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(weekday: u8, hour: u16, minute: u16) -> LocalTime {
+        LocalTime {
+            weekday,
+            minute: hour * 60 + minute,
+        }
+    }
+
+    #[test]
+    fn parse_and_match_windows() {
+        let office: Window = "mon-fri 08:00-18:00".parse().unwrap();
+        assert!(office.contains(at(0, 8, 0)));
+        assert!(office.contains(at(4, 17, 59)));
+        assert!(!office.contains(at(4, 18, 0)));
+        assert!(!office.contains(at(5, 12, 0)));
+        assert_eq!(office.to_string(), "mon,tue,wed,thu,fri 08:00-18:00");
+
+        // Friday night runs into Saturday morning, Sunday night doesn't reach Monday
+        let night: Window = "fri-sat 22:00-02:00".parse().unwrap();
+        assert!(night.contains(at(4, 23, 0)));
+        assert!(night.contains(at(5, 1, 0)));
+        assert!(night.contains(at(6, 1, 59)));
+        assert!(!night.contains(at(0, 1, 0)));
+        assert!(!night.contains(at(4, 1, 0)));
+
+        let daily: Window = "00:00-24:00".parse().unwrap();
+        assert!(daily.contains(at(6, 23, 59)));
+        assert_eq!(daily.to_string(), "00:00-24:00");
+
+        let weekend: Window = "sat,SUN 10:00-14:00".parse().unwrap();
+        assert!(weekend.contains(at(6, 10, 0)));
+        assert!(!weekend.contains(at(0, 10, 0)));
+
+        assert!("mon-fri".parse::<Window>().is_err());
+        assert!("mon-fry 08:00-18:00".parse::<Window>().is_err());
+        assert!("08:00-08:00".parse::<Window>().is_err());
+        assert!("08:60-09:00".parse::<Window>().is_err());
+        assert!("08:00-24:01".parse::<Window>().is_err());
+        assert!("1100:00-02:00".parse::<Window>().is_err());
+    }
+}
@@ -0,0 +1,116 @@
+// Host ACLs of a rule. Packets that arrive on one of the rule's sources must come from an
+// address in `allowed_sources` and, if `allowed_macs` is set, from one of those MAC addresses.
+// MACs and IPv6 senders are only seen by the packet backend, so the config is rejected if it
+// sets `allowed_macs` or lists IPv6 networks with another backend.
+use super::MdnsConfig;
+use serde::{Deserialize, Serialize};
+use std::{fmt, net::IpAddr, str::FromStr};
+
+/// An Ethernet address written as `aa:bb:cc:dd:ee:ff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MacAddress(pub [u8; 6]);
+
+impl FromStr for MacAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid MAC address {:?}", s);
+        let mut out = [0; 6];
+        let mut parts = s.split([':', '-']);
+
+        for byte in &mut out {
+            let part = parts.next().ok_or_else(invalid)?;
+            if part.len() != 2 {
+                return Err(invalid());
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(Self(out)),
+        }
+    }
+}
+
+impl TryFrom<String> for MacAddress {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<MacAddress> for String {
+    fn from(mac: MacAddress) -> Self {
+        mac.to_string()
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl MdnsConfig {
+    /// Returns true if the ACLs let a packet from this sender through. A sender whose address
+    /// or MAC isn't known is rejected by a rule that checks it.
+    pub fn allows_sender(&self, address: Option<IpAddr>, mac: Option<[u8; 6]>) -> bool {
+        let address_allowed = self.allowed_sources.is_empty()
+            || address.is_some_and(|address| {
+                self.allowed_sources
+                    .iter()
+                    .any(|net| net.contains(&address))
+            });
+
+        let mac_allowed = self.allowed_macs.is_empty()
+            || mac.is_some_and(|mac| self.allowed_macs.contains(&MacAddress(mac)));
+
+        address_allowed && mac_allowed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_sender_acls() {
+        let conf: MdnsConfig = toml::from_str(
+            r#"
+            sources = ["eth0"]
+            destinations = ["eth1"]
+            allowed_sources = ["192.0.2.10/32", "fd00:1::/64"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(conf.allows_sender(Some("192.0.2.10".parse().unwrap()), None));
+        assert!(!conf.allows_sender(Some("192.0.2.11".parse().unwrap()), None));
+        assert!(conf.allows_sender(Some("fd00:1::5".parse().unwrap()), None));
+        assert!(!conf.allows_sender(None, None));
+
+        let conf = MdnsConfig {
+            allowed_macs: vec!["02:00:5e:10:00:01".parse().unwrap()],
+            ..conf
+        };
+
+        assert!(conf.allows_sender(
+            Some("192.0.2.10".parse().unwrap()),
+            Some([0x02, 0x00, 0x5e, 0x10, 0x00, 0x01])
+        ));
+        assert!(!conf.allows_sender(Some("192.0.2.10".parse().unwrap()), None));
+        assert!("02:00:5e:10:00".parse::<MacAddress>().is_err());
+        assert!("02:00:5e:10:00:01:02".parse::<MacAddress>().is_err());
+        assert_eq!(
+            "02-00-5E-10-00-01"
+                .parse::<MacAddress>()
+                .unwrap()
+                .to_string(),
+            "02:00:5e:10:00:01"
+        );
+    }
+}
@@ -0,0 +1,597 @@
+// Checks for mistakes that deserialize fine but break forwarding. Findings are tied to a
+// `Location` in the config, which is resolved to a TOML span when the config came from a file.
+use super::{is_glob, Config, Diagnostic, MdnsConfig, Severity, Source};
+use crate::interfaces::InterfaceTable;
+use crate::socket::Backend;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::Range,
+};
+use toml::Spanned;
+
+/// Part of the config a finding is about. Rules are numbered across every included file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Location {
+    Rule(usize),
+    Sources(usize),
+    Destinations(usize),
+    Source(usize, String),
+    Destination(usize, String),
+    Filter(usize, String),
+    Deny(usize, String),
+    AllowedSource(usize, String),
+    AllowedMacs(usize),
+    FilterSetRef(usize, String),
+    Zone(String),
+    ZoneMember(String, String),
+    FilterSet(String),
+    FilterSetEntry(String, String),
+}
+
+type SpannedList = Spanned<Vec<Spanned<String>>>;
+
+/// Positions of the parts of one file that findings point at. This is deserialized from the
+/// same source as `Config` so that `Config` itself stays free of spans.
+#[derive(Debug, Default, Deserialize)]
+struct ConfigSpans {
+    #[serde(default)]
+    mdns: Vec<Spanned<RuleSpans>>,
+    #[serde(default)]
+    zones: HashMap<Spanned<String>, SpannedList>,
+    #[serde(default)]
+    filter_sets: HashMap<Spanned<String>, FilterSetSpans>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleSpans {
+    sources: Option<SpannedList>,
+    destinations: Option<SpannedList>,
+    filters: Option<SpannedList>,
+    deny: Option<SpannedList>,
+    allowed_sources: Option<SpannedList>,
+    allowed_macs: Option<SpannedList>,
+    filter_sets: Option<SpannedList>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FilterSetSpans {
+    filters: Option<SpannedList>,
+}
+
+impl ConfigSpans {
+    /// Span of `location` if it is in this file. `first_rule` is the number of rules in the
+    /// files merged before this one.
+    fn find(&self, location: &Location, first_rule: usize) -> Option<Range<usize>> {
+        let find_name = |list: Option<&SpannedList>, name: &str| {
+            list?
+                .get_ref()
+                .iter()
+                .find(|n| n.get_ref() == name)
+                .map(Spanned::span)
+        };
+
+        let zone = |name: &str| self.zones.iter().find(|(k, _)| k.get_ref() == name);
+        let filter_set = |name: &str| self.filter_sets.iter().find(|(k, _)| k.get_ref() == name);
+
+        let (index, span) = match location {
+            Location::Zone(name) => return zone(name).map(|(k, _)| k.span()),
+            Location::ZoneMember(name, member) => {
+                let (k, members) = zone(name)?;
+                return Some(find_name(Some(members), member).unwrap_or(k.span()));
+            }
+            Location::FilterSet(name) => return filter_set(name).map(|(k, _)| k.span()),
+            Location::FilterSetEntry(name, filter) => {
+                let (k, set) = filter_set(name)?;
+                return Some(find_name(set.filters.as_ref(), filter).unwrap_or(k.span()));
+            }
+            Location::Rule(i) => (*i, None),
+            Location::Sources(i) => (
+                *i,
+                self.rule(*i, first_rule)?
+                    .sources
+                    .as_ref()
+                    .map(Spanned::span),
+            ),
+            Location::Destinations(i) => (
+                *i,
+                self.rule(*i, first_rule)?
+                    .destinations
+                    .as_ref()
+                    .map(Spanned::span),
+            ),
+            Location::Source(i, name) => (
+                *i,
+                find_name(self.rule(*i, first_rule)?.sources.as_ref(), name),
+            ),
+            Location::Destination(i, name) => (
+                *i,
+                find_name(self.rule(*i, first_rule)?.destinations.as_ref(), name),
+            ),
+            Location::Filter(i, name) => (
+                *i,
+                find_name(self.rule(*i, first_rule)?.filters.as_ref(), name),
+            ),
+            Location::Deny(i, name) => (
+                *i,
+                find_name(self.rule(*i, first_rule)?.deny.as_ref(), name),
+            ),
+            Location::AllowedSource(i, net) => (
+                *i,
+                find_name(self.rule(*i, first_rule)?.allowed_sources.as_ref(), net),
+            ),
+            Location::AllowedMacs(i) => (
+                *i,
+                self.rule(*i, first_rule)?
+                    .allowed_macs
+                    .as_ref()
+                    .map(Spanned::span),
+            ),
+            Location::FilterSetRef(i, name) => (
+                *i,
+                find_name(self.rule(*i, first_rule)?.filter_sets.as_ref(), name),
+            ),
+        };
+
+        let rule = self.mdns.get(index.checked_sub(first_rule)?)?;
+        span.or(Some(rule.span()))
+    }
+
+    fn rule(&self, index: usize, first_rule: usize) -> Option<&RuleSpans> {
+        self.mdns
+            .get(index.checked_sub(first_rule)?)
+            .map(Spanned::get_ref)
+    }
+}
+
+impl Config {
+    /// Checks the rules for mistakes that would make them never match or loop packets.
+    /// Interface names are only checked if `interfaces` is given.
+    pub fn validate(&self, interfaces: Option<&InterfaceTable>) -> Vec<Diagnostic> {
+        self.check(interfaces)
+            .into_iter()
+            .map(|(_, diagnostic)| diagnostic)
+            .collect()
+    }
+
+    /// Same as `validate` but the findings point at their place in `sources`, the main file
+    /// followed by the files it includes. `conflicts` are findings from merging the includes
+    /// along with the index of the source they are in. The findings are ordered by position.
+    pub(super) fn validate_sources(
+        &self,
+        sources: &[Source],
+        conflicts: Vec<(usize, Location, Diagnostic)>,
+        interfaces: Option<&InterfaceTable>,
+    ) -> Vec<Diagnostic> {
+        let spans: Vec<ConfigSpans> = sources
+            .iter()
+            .map(|source| toml::from_str(&source.contents).unwrap_or_default())
+            .collect();
+
+        let locate = |location: &Location| {
+            let mut first_rule = 0;
+            for (index, spans) in spans.iter().enumerate() {
+                if let Some(span) = spans.find(location, first_rule) {
+                    return (index, Some(span));
+                }
+                first_rule += spans.mdns.len();
+            }
+            (0, None)
+        };
+
+        let mut diagnostics: Vec<_> = self
+            .check(interfaces)
+            .into_iter()
+            .map(|(location, diagnostic)| (locate(&location), diagnostic))
+            .chain(conflicts.into_iter().map(|(index, location, diagnostic)| {
+                ((index, spans[index].find(&location, 0)), diagnostic)
+            }))
+            .map(|((index, span), diagnostic)| (index, diagnostic.at(&sources[index], span)))
+            .collect();
+
+        diagnostics.sort_by_key(|(index, d)| (*index, d.span.as_ref().map(|s| s.start)));
+        diagnostics.into_iter().map(|(_, d)| d).collect()
+    }
+
+    fn check(&self, interfaces: Option<&InterfaceTable>) -> Vec<(Location, Diagnostic)> {
+        let mut out = vec![];
+        let mut push = |location, severity, message: String| {
+            out.push((location, Diagnostic::new(severity, message)))
+        };
+
+        for (zone, members) in &self.zones {
+            if members.is_empty() {
+                push(
+                    Location::Zone(zone.clone()),
+                    Severity::Warning,
+                    format!("zone {} has no interfaces", zone),
+                );
+            }
+
+            if interfaces.is_some_and(|i| i.by_name(zone).is_some()) {
+                push(
+                    Location::Zone(zone.clone()),
+                    Severity::Warning,
+                    format!("zone {} hides the interface with the same name", zone),
+                );
+            }
+
+            for member in members {
+                let location = Location::ZoneMember(zone.clone(), member.clone());
+
+                if self.zones.contains_key(member) {
+                    push(
+                        location,
+                        Severity::Error,
+                        format!(
+                            "zone {} contains zone {}, zones can't be nested",
+                            zone, member
+                        ),
+                    );
+                } else if let Some(message) = interfaces.and_then(|i| check_interface(i, member)) {
+                    push(location, Severity::Warning, message);
+                }
+            }
+        }
+
+        for (name, set) in &self.filter_sets {
+            for filter in &set.filters {
+                if let Some((severity, message)) = check_filter(filter) {
+                    push(
+                        Location::FilterSetEntry(name.clone(), filter.clone()),
+                        severity,
+                        message,
+                    );
+                }
+            }
+        }
+
+        for (i, rule) in self.mdns.iter().enumerate() {
+            if rule.sources.is_empty() {
+                push(
+                    Location::Sources(i),
+                    Severity::Error,
+                    "rule has no sources".to_string(),
+                );
+            }
+
+            if rule.destinations.is_empty() {
+                push(
+                    Location::Destinations(i),
+                    Severity::Error,
+                    "rule has no destinations".to_string(),
+                );
+            }
+
+            let destinations: BTreeSet<_> = self.patterns(&rule.destinations).collect();
+            for entry in &rule.sources {
+                let entries = std::slice::from_ref(entry);
+                for pattern in self.patterns(entries) {
+                    if destinations.contains(pattern) {
+                        push(
+                            Location::Source(i, entry.clone()),
+                            Severity::Error,
+                            format!("interface {} is both a source and a destination", pattern),
+                        );
+                    }
+                }
+            }
+
+            if let Some(interfaces) = interfaces {
+                let entries = rule
+                    .sources
+                    .iter()
+                    .map(|n| (n, Location::Source(i, n.clone())))
+                    .chain(
+                        rule.destinations
+                            .iter()
+                            .map(|n| (n, Location::Destination(i, n.clone()))),
+                    )
+                    // Zone members are checked once for the zone
+                    .filter(|(n, _)| !self.zones.contains_key(*n));
+
+                for (name, location) in entries {
+                    if let Some(message) = check_interface(interfaces, name) {
+                        push(location, Severity::Warning, message);
+                    }
+                }
+            }
+
+            for filter in &rule.filters {
+                if let Some((severity, message)) = check_filter(filter) {
+                    push(Location::Filter(i, filter.clone()), severity, message);
+                }
+            }
+
+            for entry in &rule.deny {
+                if let Some((severity, message)) = check_filter(entry) {
+                    push(Location::Deny(i, entry.clone()), severity, message);
+                } else if rule.filters.iter().any(|f| f.eq_ignore_ascii_case(entry)) {
+                    push(
+                        Location::Deny(i, entry.clone()),
+                        Severity::Warning,
+                        format!("filter {:?} is both allowed and denied, deny wins", entry),
+                    );
+                }
+            }
+
+            // Only the packet backend receives over IPv6
+            for net in rule
+                .allowed_sources
+                .iter()
+                .filter(|net| net.addr().is_ipv6())
+                .filter(|_| self.sockets.mdns.backend != Backend::Packet)
+            {
+                push(
+                    Location::AllowedSource(i, net.to_string()),
+                    Severity::Error,
+                    format!(
+                        "{} needs the packet backend, other backends only receive over IPv4",
+                        net
+                    ),
+                );
+            }
+
+            if !rule.allowed_macs.is_empty() && self.sockets.mdns.backend != Backend::Packet {
+                push(
+                    Location::AllowedMacs(i),
+                    Severity::Error,
+                    "allowed_macs needs the packet backend, other backends don't see MAC addresses"
+                        .to_string(),
+                );
+            }
+
+            if rule.send_goodbyes && rule.schedule.is_empty() {
+                push(
+                    Location::Rule(i),
+                    Severity::Warning,
+                    "send_goodbyes has no effect without a schedule".to_string(),
+                );
+            }
+
+            for name in &rule.filter_sets {
+                if !self.filter_sets.contains_key(name) {
+                    push(
+                        Location::FilterSetRef(i, name.clone()),
+                        Severity::Error,
+                        format!("filter set {} is not defined", name),
+                    );
+                }
+            }
+
+            if let Some(first) = self.mdns[..i].iter().position(|r| same_rule(r, rule)) {
+                push(
+                    Location::Rule(i),
+                    Severity::Warning,
+                    format!("rule is a duplicate of rule {}", first + 1),
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// A missing interface is only a warning since it may be created later
+fn check_interface(interfaces: &InterfaceTable, pattern: &str) -> Option<String> {
+    if is_glob(pattern) {
+        let matches = interfaces
+            .iter()
+            .any(|i| super::glob_match(pattern, &i.name));
+        (!matches).then(|| format!("no interface matches {}", pattern))
+    } else {
+        let exists = interfaces.by_name(pattern).is_some();
+        (!exists).then(|| format!("interface {} does not exist", pattern))
+    }
+}
+
+/// Names are compared without the root label, so `printer.local.` would never match
+fn check_filter(filter: &str) -> Option<(Severity, String)> {
+    if filter.is_empty() {
+        return Some((Severity::Error, "filter is empty".to_string()));
+    }
+
+    if filter.ends_with('.') {
+        return Some((
+            Severity::Error,
+            format!(
+                "filter {:?} has a trailing dot and never matches, use {:?}",
+                filter,
+                filter.trim_end_matches('.')
+            ),
+        ));
+    }
+
+    if !filter.to_ascii_lowercase().ends_with(".local") {
+        return Some((
+            Severity::Warning,
+            format!("filter {:?} is not a .local name", filter),
+        ));
+    }
+
+    None
+}
+
+fn same_rule(a: &MdnsConfig, b: &MdnsConfig) -> bool {
+    let set = |list: &[String]| list.iter().cloned().collect::<BTreeSet<_>>();
+
+    set(&a.sources) == set(&b.sources)
+        && set(&a.destinations) == set(&b.destinations)
+        && a.filters == b.filters
+        && a.deny == b.deny
+        && a.allowed_sources == b.allowed_sources
+        && a.allowed_macs == b.allowed_macs
+        && a.schedule == b.schedule
+        && a.send_goodbyes == b.send_goodbyes
+        && set(&a.filter_sets) == set(&b.filter_sets)
+        && a.txt_filters == b.txt_filters
+        && a.strip_edns_options == b.strip_edns_options
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ConfigError;
+
+    fn validate(contents: &str) -> Vec<Diagnostic> {
+        let config: Config = toml::from_str(contents).unwrap();
+        let source = Source {
+            file: None,
+            contents: contents.to_string(),
+        };
+
+        config.validate_sources(&[source], vec![], None)
+    }
+
+    #[test]
+    fn report_errors_with_lines() {
+        let source = r#"
+[[mdns]]
+sources = ["eth0"]
+destinations = ["eth1", "eth0"]
+filters = ["printer.local."]
+
+[[mdns]]
+sources = []
+destinations = ["eth1"]
+filters = []
+"#;
+
+        let diagnostics = validate(source);
+
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.severity, d.message.as_str()))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Some(3),
+                    Severity::Error,
+                    "interface eth0 is both a source and a destination"
+                ),
+                (
+                    Some(5),
+                    Severity::Error,
+                    "filter \"printer.local.\" has a trailing dot and never matches, use \"printer.local\""
+                ),
+                (Some(8), Severity::Error, "rule has no sources"),
+            ]
+        );
+    }
+
+    #[test]
+    fn warn_about_duplicates_and_non_local_filters() {
+        let source = r#"
+[[mdns]]
+sources = ["eth0"]
+destinations = ["eth1", "eth2"]
+filters = ["printer"]
+
+[[mdns]]
+sources = ["eth0"]
+destinations = ["eth2", "eth1"]
+filters = ["printer"]
+"#;
+
+        let (_, warnings) = Config::from_toml(source).unwrap();
+        let found: Vec<_> = warnings
+            .iter()
+            .filter(|d| !d.message.ends_with("does not exist"))
+            .map(|d| (d.line, d.message.as_str()))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (Some(5), "filter \"printer\" is not a .local name"),
+                (Some(7), "rule is a duplicate of rule 1"),
+                (Some(10), "filter \"printer\" is not a .local name"),
+            ]
+        );
+
+        let source = source.replace("\"printer\"", "\"printer.local.\"");
+        assert!(matches!(
+            Config::from_toml(&source),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn report_zone_errors() {
+        let source = r#"
+[zones]
+staff = ["eth1"]
+all = ["staff", "eth2"]
+
+[[mdns]]
+sources = ["eth1"]
+destinations = ["staff"]
+filters = []
+"#;
+
+        let found: Vec<_> = validate(source)
+            .into_iter()
+            .map(|d| (d.line, d.message))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Some(4),
+                    "zone all contains zone staff, zones can't be nested".to_string()
+                ),
+                (
+                    Some(7),
+                    "interface eth1 is both a source and a destination".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_sender_acls() {
+        let source = r#"
+[[mdns]]
+sources = ["eth0"]
+destinations = ["eth1"]
+allowed_sources = ["192.0.2.0/24", "fd00:1::/64"]
+allowed_macs = ["02:00:5e:10:00:01"]
+"#;
+
+        let found: Vec<_> = validate(source)
+            .into_iter()
+            .map(|d| (d.line, d.severity, d.message))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Some(5),
+                    Severity::Error,
+                    "fd00:1::/64 needs the packet backend, other backends only receive over IPv4"
+                        .to_string()
+                ),
+                (
+                    Some(6),
+                    Severity::Error,
+                    "allowed_macs needs the packet backend, other backends don't see MAC addresses"
+                        .to_string()
+                )
+            ]
+        );
+
+        let source = format!("[sockets.mdns]\nbackend = \"packet\"\n{}", source);
+        assert!(validate(&source).is_empty());
+
+        let source = source.replace("02:00:5e:10:00:01", "02:00:5e:10:00");
+        let Err(ConfigError::Parse(diagnostic)) = Config::from_toml(&source) else {
+            panic!("invalid MAC address was accepted");
+        };
+        assert_eq!(diagnostic.line, Some(8));
+    }
+}
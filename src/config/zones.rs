@@ -0,0 +1,125 @@
+// Zones name a group of interfaces, e.g. `iot = ["eth70", "vlan*"]`. Rules can use a zone
+// anywhere they take an interface name. Interface names and zone members may be globs where `*`
+// matches any run of characters and `?` matches one.
+use super::Config;
+use std::collections::BTreeSet;
+
+impl Config {
+    /// Interface names and globs that a list of sources or destinations stands for. Zones are
+    /// replaced by their members, anything else is an interface name or glob.
+    pub fn patterns<'a>(&'a self, entries: &'a [String]) -> impl Iterator<Item = &'a str> {
+        entries
+            .iter()
+            .flat_map(move |entry| match self.zones.get(entry) {
+                Some(members) => members.iter().map(String::as_str).collect::<Vec<_>>(),
+                None => vec![entry.as_str()],
+            })
+    }
+
+    /// Returns true if the interface is one of `entries`, directly, through a glob or as a
+    /// member of a zone
+    pub fn matches_interface(&self, entries: &[String], name: &str) -> bool {
+        self.patterns(entries)
+            .any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Returns true if any rule uses the interface as a source or destination
+    pub fn uses_interface(&self, name: &str) -> bool {
+        self.mdns.iter().any(|conf| {
+            self.matches_interface(&conf.sources, name)
+                || self.matches_interface(&conf.destinations, name)
+        })
+    }
+
+    /// Interface names and globs used as a source or destination by any rule
+    pub fn interface_patterns(&self) -> BTreeSet<&str> {
+        self.mdns
+            .iter()
+            .flat_map(|conf| {
+                self.patterns(&conf.sources)
+                    .chain(self.patterns(&conf.destinations))
+            })
+            .collect()
+    }
+}
+
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the part of `name` it has consumed so far
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, consumed)) => {
+                    p = after;
+                    n = consumed + 1;
+                    star = Some((after, consumed + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_globs() {
+        assert!(glob_match("eth0", "eth0"));
+        assert!(!glob_match("eth0", "eth01"));
+        assert!(glob_match("vlan*", "vlan"));
+        assert!(glob_match("vlan*", "vlan20"));
+        assert!(glob_match("br-*", "br-4f2a"));
+        assert!(!glob_match("br-*", "bridge"));
+        assert!(glob_match("eth?0", "eth10"));
+        assert!(glob_match("*.100", "eth0.100"));
+        assert!(glob_match("e*h*0", "eth10"));
+        assert!(!glob_match("e*h*0", "eth1"));
+    }
+
+    #[test]
+    fn resolve_zones() {
+        let config: Config = toml::from_str(
+            r#"
+            [zones]
+            iot = ["eth70", "vlan7*"]
+            staff = ["eth10"]
+
+            [[mdns]]
+            sources = ["iot"]
+            destinations = ["staff", "br-*"]
+            filters = []
+            "#,
+        )
+        .unwrap();
+
+        let rule = &config.mdns[0];
+        assert!(config.matches_interface(&rule.sources, "vlan70"));
+        assert!(!config.matches_interface(&rule.sources, "iot"));
+        assert!(config.matches_interface(&rule.destinations, "br-lan"));
+        assert!(config.uses_interface("eth10"));
+        assert!(!config.uses_interface("eth20"));
+        assert_eq!(
+            config.interface_patterns().into_iter().collect::<Vec<_>>(),
+            vec!["br-*", "eth10", "eth70", "vlan7*"]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+// Control API on a Unix socket. Clients send one JSON request per line and get one JSON response
+// per line, e.g. `{"command":"disable","rule":2}`. Connections are served from their own threads
+// and requests are handed to the event loop through a channel, so they are answered on the same
+// thread that forwards packets. Rules are numbered from 1 like in the logs and metrics.
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        io::{AsRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Every rule with the interfaces it currently resolves to
+    Rules,
+    /// Packets forwarded and dropped per rule
+    Counters,
+    /// Names of the most recently forwarded packets, oldest first
+    Recent,
+    /// Reads the config file again, like SIGHUP
+    Reload,
+    /// Turns a rule back on. Every rule is on again after a reload.
+    Enable { rule: usize },
+    /// Turns a rule off until it is enabled again or the config is reloaded
+    Disable { rule: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Rules(Vec<RuleStatus>),
+    Counters(Vec<RuleCounters>),
+    Recent(Vec<Forwarded>),
+    Done,
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleStatus {
+    pub rule: usize,
+    pub enabled: bool,
+    /// False while the rule is outside its schedule
+    pub active: bool,
+    pub sources: Vec<String>,
+    pub destinations: Vec<String>,
+    /// Interfaces that `sources` match right now
+    pub source_interfaces: Vec<String>,
+    /// Interfaces that `destinations` match right now
+    pub destination_interfaces: Vec<String>,
+    pub filters: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleCounters {
+    pub rule: usize,
+    pub forwarded: u64,
+    /// Dropped packets by reason, e.g. `denied`
+    pub dropped: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Forwarded {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub rule: usize,
+    /// Question names of a query or answer names of a response
+    pub names: Vec<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+type Pending = (Request, Sender<Response>);
+
+/// Listening control socket. Register `as_raw_fd` with the event loop and call `handle` when
+/// it is readable.
+pub struct ControlSocket {
+    requests: Receiver<Pending>,
+    wake: UnixStream,
+}
+
+impl ControlSocket {
+    /// Binds `path`, replacing a socket left behind by an earlier run. Fails if another
+    /// instance is listening on it or if something other than a socket is there. Only the owner
+    /// can connect. Signals should be blocked before this is called, see `Reactor::add_signals`.
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if !metadata.file_type().is_socket() => {
+                return Err(io::Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            Ok(_) if UnixStream::connect(path).is_ok() => {
+                return Err(io::Error::new(
+                    ErrorKind::AddrInUse,
+                    format!("another instance is listening on {}", path.display()),
+                ));
+            }
+            Ok(_) => fs::remove_file(path)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        // The socket is created with the umask applied, so nobody else can connect in the
+        // moment before its permissions are set
+        let umask = unsafe { libc::umask(0o177) };
+        let listener = UnixListener::bind(path);
+        unsafe { libc::umask(umask) };
+
+        let listener = listener?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        info!("control socket listening on {}", path.display());
+
+        let (wake, notify) = UnixStream::pair()?;
+        wake.set_nonblocking(true)?;
+        notify.set_nonblocking(true)?;
+
+        let (sender, requests) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("control".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            warn!("error in accepting control connection: {}", e);
+                            continue;
+                        }
+                    };
+
+                    let (sender, notify) = match notify.try_clone() {
+                        Ok(notify) => (sender.clone(), notify),
+                        Err(e) => {
+                            warn!("error in accepting control connection: {}", e);
+                            continue;
+                        }
+                    };
+
+                    let spawned = thread::Builder::new()
+                        .name("control-client".to_string())
+                        .spawn(move || {
+                            if let Err(e) = serve_client(stream, &sender, &notify) {
+                                debug!("control connection closed: {}", e);
+                            }
+                        });
+
+                    if let Err(e) = spawned {
+                        warn!("error in starting control connection thread: {}", e);
+                    }
+                }
+            })?;
+
+        Ok(Self { requests, wake })
+    }
+
+    /// Answers every pending request with `answer`
+    pub fn handle(&self, mut answer: impl FnMut(Request) -> Response) {
+        let mut buf = [0; 64];
+        while let Ok(read) = (&self.wake).read(&mut buf) {
+            if read == 0 {
+                break;
+            }
+        }
+
+        for (request, reply) in self.requests.try_iter() {
+            // The client may have gone away in the meantime
+            let _ = reply.send(answer(request));
+        }
+    }
+}
+
+impl AsRawFd for ControlSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.wake.as_raw_fd()
+    }
+}
+
+fn serve_client(
+    stream: UnixStream,
+    requests: &Sender<Pending>,
+    notify: &UnixStream,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => {
+                let (reply, response) = mpsc::channel();
+                requests
+                    .send((request, reply))
+                    .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, "daemon stopped"))?;
+
+                // A full buffer already has a wakeup pending
+                match (&*notify).write(&[1]) {
+                    Err(e) if e.kind() != ErrorKind::WouldBlock => return Err(e),
+                    _ => {}
+                }
+
+                response
+                    .recv()
+                    .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, "daemon stopped"))?
+            }
+            Err(e) => Response::Error(format!("invalid request: {}", e)),
+        };
+
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+/// Sends one request to the control socket at `path` and waits for the response
+pub fn request(path: &Path, request: &Request) -> io::Result<Response> {
+    let mut stream = UnixStream::connect(path)?;
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+
+    serde_json::from_str(&line).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn answer_requests_over_socket() {
+        let path =
+            std::env::temp_dir().join(format!("multicaster-control-{}.sock", std::process::id()));
+        // A socket left behind by an earlier run is replaced
+        drop(UnixListener::bind(&path).unwrap());
+        let control = ControlSocket::bind(&path).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert_eq!(
+            ControlSocket::bind(&path).err().map(|e| e.kind()),
+            Some(ErrorKind::AddrInUse)
+        );
+
+        let client = {
+            let path = path.clone();
+            thread::spawn(move || request(&path, &Request::Disable { rule: 2 }).unwrap())
+        };
+
+        // Wait for the request like the event loop would
+        let mut answered = vec![];
+        while answered.is_empty() {
+            control.handle(|request| {
+                answered.push(request);
+                Response::Done
+            });
+            thread::yield_now();
+        }
+
+        assert_eq!(client.join().unwrap(), Response::Done);
+        assert_eq!(answered, vec![Request::Disable { rule: 2 }]);
+        assert_eq!(
+            serde_json::to_string(&Request::Disable { rule: 2 }).unwrap(),
+            r#"{"command":"disable","rule":2}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"command":"rules"}"#).unwrap(),
+            Request::Rules
+        );
+
+        fs::remove_file(&path).unwrap();
+
+        fs::write(&path, "not a socket").unwrap();
+        assert_eq!(
+            ControlSocket::bind(&path).err().map(|e| e.kind()),
+            Some(ErrorKind::AlreadyExists)
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
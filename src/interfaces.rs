@@ -0,0 +1,148 @@
+use crate::netlink::InterfaceEvent;
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    io::Result as IoResult,
+    net::{IpAddr, Ipv4Addr},
+};
+
+#[derive(Debug, Clone)]
+pub struct InterfaceInfo {
+    pub index: u32,
+    pub name: String,
+    pub addresses: Vec<IpAddr>,
+}
+
+impl InterfaceInfo {
+    pub fn has_ipv4(&self) -> bool {
+        self.addresses.iter().any(|a| a.is_ipv4())
+    }
+
+    /// The first IPv4 address of the interface, which the kernel treats as its primary address
+    pub fn primary_ipv4(&self) -> Option<Ipv4Addr> {
+        self.addresses.iter().find_map(|a| match a {
+            IpAddr::V4(a) => Some(*a),
+            IpAddr::V6(_) => None,
+        })
+    }
+}
+
+/// Cached name <-> index <-> address map of the interfaces on this machine. It is loaded once
+/// and then kept up to date with netlink events so the hot path doesn't have to ask the kernel.
+#[derive(Debug, Default, Clone)]
+pub struct InterfaceTable {
+    interfaces: HashMap<u32, InterfaceInfo>,
+}
+
+impl InterfaceTable {
+    pub fn load() -> IoResult<Self> {
+        let mut table = Self::default();
+
+        // Interfaces without any address are only listed by if_nameindex
+        for interface in nix::net::if_::if_nameindex()
+            .map_err(std::io::Error::from)?
+            .iter()
+        {
+            table.interfaces.insert(
+                interface.index(),
+                InterfaceInfo {
+                    index: interface.index(),
+                    name: interface.name().to_string_lossy().into_owned(),
+                    addresses: vec![],
+                },
+            );
+        }
+
+        for interface in get_if_addrs::get_if_addrs()? {
+            if let Some(info) = table
+                .interfaces
+                .values_mut()
+                .find(|i| i.name == interface.name)
+            {
+                info.addresses.push(interface.ip());
+            }
+        }
+
+        Ok(table)
+    }
+
+    pub fn get(&self, index: u32) -> Option<&InterfaceInfo> {
+        self.interfaces.get(&index)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&InterfaceInfo> {
+        self.interfaces.values().find(|i| i.name == name)
+    }
+
+    pub fn name(&self, index: u32) -> Option<&str> {
+        self.get(index).map(|i| i.name.as_str())
+    }
+
+    pub fn index(&self, name: &str) -> Option<u32> {
+        self.by_name(name).map(|i| i.index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &InterfaceInfo> {
+        self.interfaces.values()
+    }
+
+    /// Updates the table with a netlink event
+    pub fn apply(&mut self, event: &InterfaceEvent) {
+        match event {
+            InterfaceEvent::LinkAdded { index, name } => {
+                self.interfaces
+                    .entry(*index)
+                    .or_insert_with(|| InterfaceInfo {
+                        index: *index,
+                        name: name.clone(),
+                        addresses: vec![],
+                    })
+                    .name
+                    .clone_from(name);
+            }
+
+            InterfaceEvent::LinkRemoved { index } => {
+                self.interfaces.remove(index);
+            }
+
+            InterfaceEvent::AddressAdded { index, address } => {
+                let name = if_indextoname(*index).unwrap_or_default();
+
+                let info = self
+                    .interfaces
+                    .entry(*index)
+                    .or_insert_with(|| InterfaceInfo {
+                        index: *index,
+                        name,
+                        addresses: vec![],
+                    });
+
+                if !info.addresses.contains(address) {
+                    info.addresses.push(*address);
+                }
+            }
+
+            InterfaceEvent::AddressRemoved { index, address } => {
+                if let Some(info) = self.interfaces.get_mut(index) {
+                    info.addresses.retain(|a| a != address);
+                }
+            }
+
+            InterfaceEvent::Overrun => {}
+        }
+    }
+}
+
+pub fn if_indextoname(index: u32) -> Option<String> {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+
+    let ptr = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+    if ptr.is_null() {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .ok()
+        .map(str::to_owned)
+}
@@ -0,0 +1,16 @@
+// TODO(ishan): Eventually we'll have a listener and transmitter module for every thing we want to
+// support. So, 1 for MDNS, another for WSDD?
+// Or a common listener/transmitter and then different modules to parse and transmit each type of
+// traffic
+pub mod config;
+pub mod control;
+pub mod interfaces;
+pub use config::*;
+pub mod mdns;
+pub use mdns::*;
+pub mod metrics;
+pub mod netlink;
+mod parser;
+pub mod reactor;
+pub mod socket;
+pub use parser::*;
@@ -1,29 +1,230 @@
-#![feature(ip_bits)]
-#![feature(async_closure)]
-// TODO(ishan): Eventually we'll have a listener and transmitter module for every thing we want to
-// support. So, 1 for MDNS, another for WSDD?
-// Or a common listener/transmitter and then different modules to parse and transmit each type of
-// traffic
-use log::info;
-
-pub mod config;
-pub use config::*;
-pub mod mdns;
-pub use mdns::*;
-mod parser;
-mod socket;
-pub use parser::*;
+use clap::{Parser, Subcommand};
+use log::{info, warn, LevelFilter};
+use multicaster::{
+    control::{ControlSocket, Request, Response},
+    interfaces::InterfaceTable,
+    is_glob, metrics,
+    reactor::Reactor,
+    Config, DnsPacket, Mdns,
+};
+use nix::sys::signal::Signal;
+use std::{fs, os::unix::io::AsRawFd, path::Path, process, rc::Rc};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Forwards mDNS packets between interfaces")]
+struct Cli {
+    /// Path to the config file
+    #[arg(short, long, default_value = "config.toml")]
+    config: String,
+
+    /// Log level (off, error, warn, info, debug, trace). Overrides RUST_LOG.
+    #[arg(short, long)]
+    log_level: Option<LevelFilter>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Start the daemon. This is the default.
+    Run,
+    /// Validate the config and print the effective rules
+    CheckConfig,
+    /// Show the interfaces the multicast group would be joined on
+    ListInterfaces,
+    /// Parse a packet given as hex or as the path of a file with the raw bytes
+    Decode { packet: String },
+}
 
 fn main() {
-    env_logger::init();
+    let cli = Cli::parse();
 
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(level) = cli.log_level {
+        logger.filter_level(level);
+    }
+    logger.init();
+
+    let result = match cli.command.unwrap_or(Command::Run) {
+        Command::Run => parse_config(&cli.config).map(|c| run(c, cli.config)),
+        Command::CheckConfig => check_config(&cli.config),
+        Command::ListInterfaces => parse_config(&cli.config).and_then(|c| list_interfaces(&c)),
+        Command::Decode { packet } => decode(&packet),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_config(path: &str) -> Result<Config, String> {
+    Config::parse(path).map_err(|e| e.to_string())
+}
+
+/// Runs until SIGINT or SIGTERM. SIGHUP or a reload request on the control socket re-reads the
+/// config from `path`, an invalid one is logged and the running config is kept.
+fn run(config: Config, path: String) {
     info!("starting up");
 
-    let config = Config::parse("config.toml").expect("error in parsing config");
+    let mut reactor = Reactor::new().expect("error in creating event loop");
+
+    reactor
+        .add_signals(&[Signal::SIGINT, Signal::SIGTERM], |signal, control| {
+            info!("received {}, shutting down", signal);
+            control.stop();
+        })
+        .expect("error in registering signal handler");
+
+    let metrics_address = config.metrics.listen;
+    let control_path = config.control.socket.clone();
+
+    let mdns = Mdns::new(config)
+        .register(&mut reactor)
+        .expect("error in registering mdns listener");
+
+    {
+        let (mdns, path) = (Rc::clone(&mdns), path.clone());
+        reactor
+            .add_signals(&[Signal::SIGHUP], move |_, _| {
+                info!("received SIGHUP, reloading {}", path);
+                let _ = reload(&mdns, &path);
+            })
+            .expect("error in registering signal handler");
+    }
+
+    // Started after every signal is blocked so the threads don't take them
+    if let Some(address) = metrics_address {
+        metrics::serve(mdns.metrics(), address).expect("error in starting metrics endpoint");
+    }
+
+    if let Some(socket) = &control_path {
+        let control = ControlSocket::bind(socket).expect("error in binding control socket");
+        let mdns = Rc::clone(&mdns);
+
+        reactor
+            .register(control.as_raw_fd(), move |_| {
+                control.handle(|request| match request {
+                    Request::Reload => {
+                        info!("reload requested on control socket, reloading {}", path);
+                        match reload(&mdns, &path) {
+                            Ok(()) => Response::Done,
+                            Err(e) => Response::Error(e),
+                        }
+                    }
+                    request => mdns.control(request),
+                })
+            })
+            .expect("error in registering control socket");
+    }
+
+    reactor.run().expect("error in event loop");
+
+    if let Some(socket) = control_path {
+        let _ = fs::remove_file(socket);
+    }
+}
+
+fn reload(mdns: &Mdns, path: &str) -> Result<(), String> {
+    match Config::parse(path) {
+        Ok(config) => {
+            mdns.reload(config);
+            Ok(())
+        }
+        Err(e) => {
+            warn!("keeping the running config, {}", e);
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Prints the warnings and then the config with every default filled in
+fn check_config(path: &str) -> Result<(), String> {
+    let (config, warnings) = Config::load(path).map_err(|e| e.to_string())?;
+
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+
+    let effective = toml::to_string_pretty(&config)
+        .map_err(|e| format!("error in serializing config: {}", e))?;
+
+    print!("{}", effective);
+
+    Ok(())
+}
+
+/// Prints every interface and whether the group would be joined on it. Only configured
+/// interfaces with an IPv4 address are joined.
+fn list_interfaces(config: &Config) -> Result<(), String> {
+    let table =
+        InterfaceTable::load().map_err(|e| format!("error in loading interfaces: {}", e))?;
+    let mut interfaces: Vec<_> = table.iter().collect();
+    interfaces.sort_by_key(|i| i.index);
+
+    for interface in interfaces {
+        let status = match (config.uses_interface(&interface.name), interface.has_ipv4()) {
+            (true, true) => "join",
+            (true, false) => "configured, no ipv4 address",
+            (false, _) => "not configured",
+        };
+
+        let addresses: Vec<_> = interface.addresses.iter().map(|a| a.to_string()).collect();
+
+        println!(
+            "{:>3} {:<16} {:<28} {}",
+            interface.index,
+            interface.name,
+            status,
+            addresses.join(" ")
+        );
+    }
+
+    for name in config.interface_patterns() {
+        if !is_glob(name) && table.by_name(name).is_none() {
+            println!("  - {:<16} configured, does not exist", name);
+        }
+    }
+
+    Ok(())
+}
+
+fn decode(packet: &str) -> Result<(), String> {
+    let data = if Path::new(packet).is_file() {
+        fs::read(packet).map_err(|e| format!("error in reading {}: {}", packet, e))?
+    } else {
+        parse_hex(packet)?
+    };
+
+    let packet = DnsPacket::parse(&data).map_err(|e| format!("error in parsing packet: {}", e))?;
+
+    println!("{}", packet);
+
+    Ok(())
+}
 
-    println!("{:?}", config);
+/// Parses hex bytes. Whitespace, `:` and a leading `0x` are ignored, so the output of most
+/// packet dumps can be pasted as is.
+fn parse_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = hex
+        .trim()
+        .trim_start_matches("0x")
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace() && *b != b':')
+        .collect();
 
-    let mdns_client = Mdns::new(config);
+    if !digits.len().is_multiple_of(2) {
+        return Err("hex input has an odd number of digits".to_string());
+    }
 
-    mdns_client.listener_loop();
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| format!("invalid hex byte {:?}", String::from_utf8_lossy(pair)))
+        })
+        .collect()
 }
@@ -1,137 +1,1086 @@
-use crate::socket::{Interface as MulticastInterface, MulticastOptions, MulticastSocket};
-use crate::{Config, DnsPacket};
-use dns_parser::Packet;
-use log::{info, trace, warn};
-use nix::errno::Errno;
-use std::net::Ipv4Addr;
-use std::{ffi::CString, net::SocketAddrV4};
+use crate::control::{Forwarded, Request, Response, RuleCounters, RuleStatus};
+use crate::interfaces::{InterfaceInfo, InterfaceTable};
+use crate::metrics::{self, Metrics};
+use crate::netlink::{InterfaceEvent, NetlinkSocket};
+use crate::reactor::Reactor;
+#[cfg(feature = "tokio")]
+use crate::socket::AsyncMulticastSocket;
+use crate::socket::{Interface as MulticastInterface, Message, MulticastOptions, MulticastSocket};
+use crate::{is_glob, Config, DnsPacket, LocalTime, MdnsConfig, RData, Verdict};
+use log::{debug, info, trace, warn};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{SocketAddrV4, SocketAddrV6};
+use std::os::unix::io::AsRawFd;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+mod schedule;
+
+use schedule::Schedules;
+
+/// What one rule decided for a packet. `rule` is the index into `Config::mdns`. Rules whose
+/// interfaces or entries don't match the packet make no decision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub rule: usize,
+    pub verdict: Verdict,
+}
+
+/// A packet as one rule forwards it. Denied packets have no interfaces.
+struct Forward<'a> {
+    decision: Decision,
+    data: Cow<'a, [u8]>,
+    interfaces: Vec<MulticastInterface>,
+}
 
 pub struct Mdns {
     socket: MulticastSocket,
-    config: Config,
+    /// Replaced as a whole when the config is reloaded. Packets hold on to the `Arc` they
+    /// started with, so a reload never sees a half applied config. The group is only joined on
+    /// interfaces used by a rule and packets from anywhere else are dropped.
+    config: RwLock<Arc<Config>>,
+    interfaces: RwLock<InterfaceTable>,
+    netlink: NetlinkSocket,
+    schedules: Mutex<Schedules>,
+    metrics: Arc<Metrics>,
+    /// Rules turned off through the control API, cleared on reload
+    disabled: RwLock<HashSet<usize>>,
+    recent: Mutex<VecDeque<Forwarded>>,
 }
 
 impl Mdns {
+    /// How often rule schedules are checked for closed windows
+    const SCHEDULE_INTERVAL: Duration = Duration::from_secs(10);
+    /// Forwarded packets kept for `Request::Recent`
+    const RECENT: usize = 64;
+
     pub fn new(config: Config) -> Self {
+        let table = InterfaceTable::load().expect("error in loading interface table");
+        for name in config.interface_patterns() {
+            if !is_glob(name) && table.by_name(name).is_none() {
+                warn!("interface {} from config does not exist", name);
+            }
+        }
+
+        let mut interfaces = MulticastSocket::all_interfaces().unwrap();
+        interfaces.retain(|name, _| config.uses_interface(name));
+
         // mdns
         let multicast_socket = MulticastSocket::new(
-            MulticastOptions::default(),
-            MulticastSocket::all_interfaces().unwrap(),
+            MulticastOptions {
+                nonblocking: true,
+                backend: config.sockets.mdns.backend,
+                multicast_ttl: Some(config.sockets.mdns.multicast_ttl),
+                multicast_group_v6: Some(SocketAddrV6::new(
+                    Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb),
+                    5353,
+                    0,
+                    0,
+                )),
+                ..Default::default()
+            },
+            interfaces,
             SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353),
         )
         .expect("error in creating multicast socket");
 
+        for interface in table.iter() {
+            multicast_socket.set_source_address(interface.index, interface.primary_ipv4());
+        }
+
         Self {
             socket: multicast_socket,
-            config,
+            config: RwLock::new(Arc::new(config)),
+            interfaces: RwLock::new(table),
+            netlink: NetlinkSocket::new().expect("error in creating netlink socket"),
+            schedules: Mutex::default(),
+            metrics: Arc::default(),
+            disabled: RwLock::default(),
+            recent: Mutex::default(),
         }
     }
 
-    pub fn listener_loop(&self) {
+    /// Registers the socket and the netlink subscription with the event loop. Packets are
+    /// processed as they arrive and group membership follows interfaces as they come and go.
+    /// Rule schedules are checked on a timer. The returned handle can be used to reload the
+    /// config.
+    pub fn register(self, reactor: &mut Reactor) -> std::io::Result<Rc<Self>> {
         info!("listener started");
 
+        let this = Rc::new(self);
+
+        let mdns = Rc::clone(&this);
+        reactor.register(this.socket.as_raw_fd(), move |_| mdns.read_packets())?;
+
+        let mdns = Rc::clone(&this);
+        reactor.register(this.netlink.as_raw_fd(), move |_| {
+            mdns.read_interface_events()
+        })?;
+
+        let mdns = Rc::clone(&this);
+        reactor.add_timer(Self::SCHEDULE_INTERVAL, move |_| mdns.check_schedules())?;
+
+        Ok(this)
+    }
+
+    /// Counters updated while packets are processed, see `metrics::serve`
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().unwrap())
+    }
+
+    /// Swaps in a new config. The group is joined on interfaces that are new to the config
+    /// and left on the ones that are no longer used, the rest are not touched. Socket
+    /// settings can't change while running and keep their old values.
+    pub fn reload(&self, config: Config) {
+        let new = Arc::new(config);
+        let old = std::mem::replace(&mut *self.config.write().unwrap(), Arc::clone(&new));
+
+        if old.sockets != new.sockets {
+            warn!("socket settings changed, restart to apply them");
+        }
+
+        if old.metrics != new.metrics || old.control != new.control {
+            warn!("metrics or control settings changed, restart to apply them");
+        }
+
+        let interfaces = self.interfaces.read().unwrap();
+
+        for interface in interfaces.iter().filter(|i| i.has_ipv4()) {
+            match (
+                old.uses_interface(&interface.name),
+                new.uses_interface(&interface.name),
+            ) {
+                (true, false) => self.leave(interface.index, &interface.name),
+                (false, true) => self.join(interface.index, &interface.name),
+                _ => {}
+            }
+        }
+
+        for name in new.interface_patterns() {
+            if !is_glob(name) && interfaces.by_name(name).is_none() {
+                warn!("interface {} from config does not exist", name);
+            }
+        }
+
+        self.schedules.lock().unwrap().reset();
+        self.disabled.write().unwrap().clear();
+        // Rule numbers may have changed
+        self.metrics.remove_labelled("rule");
+
+        info!("config reloaded with {} rules", new.mdns.len());
+    }
+
+    /// Sends goodbyes for the responses forwarded by rules whose schedule window has closed
+    /// since the last check
+    pub fn check_schedules(&self) {
+        let config = self.config();
+        let closed = self
+            .schedules
+            .lock()
+            .unwrap()
+            .update(&config, LocalTime::now());
+
+        for (rule, responses) in closed {
+            let conf = &config.mdns[rule];
+            info!(
+                "schedule of rule {} closed, sending {} goodbyes",
+                rule + 1,
+                responses.len()
+            );
+
+            let dst_indexes: Vec<_> = self
+                .interfaces
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|i| config.matches_interface(&conf.destinations, &i.name))
+                .map(|i| MulticastInterface::Index(i.index as i32))
+                .collect();
+
+            for data in responses {
+                let goodbye = match DnsPacket::goodbye(&data) {
+                    Ok(Some(goodbye)) => goodbye,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!("error in writing goodbye packet: {:?}", e);
+                        continue;
+                    }
+                };
+
+                let results = self.socket.send_many(&goodbye, &dst_indexes);
+                for (interface, result) in dst_indexes.iter().zip(results) {
+                    self.count_send(interface, &result);
+                    if let Err(e) = result {
+                        warn!("error in sending goodbye to {:?}: {:?}", interface, e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_interface_events(&self) {
+        match self.netlink.read_events() {
+            Ok(events) => events
+                .iter()
+                .for_each(|event| self.handle_interface_event(event)),
+            Err(e) => warn!("error in reading from netlink socket {:?}", e),
+        }
+    }
+
+    /// Updates the interface table and joins the multicast group on configured interfaces that
+    /// got their first IPv4 address or were renamed to a configured name. The group is left
+    /// again once the last IPv4 address is removed or the interface is renamed or goes away.
+    pub fn handle_interface_event(&self, event: &InterfaceEvent) {
+        trace!("interface event {:?}", event);
+
+        let config = self.config();
+        let mut interfaces = self.interfaces.write().unwrap();
+
+        let joined = |table: &InterfaceTable| -> HashMap<u32, String> {
+            table
+                .iter()
+                .filter(|i| config.uses_interface(&i.name) && i.has_ipv4())
+                .map(|i| (i.index, i.name.clone()))
+                .collect()
+        };
+        let before = joined(&interfaces);
+
+        if let InterfaceEvent::Overrun = event {
+            warn!("netlink socket overrun, reloading interfaces");
+
+            let table = match InterfaceTable::load() {
+                Ok(table) => table,
+                Err(e) => {
+                    warn!("error in loading interface table {:?}", e);
+                    return;
+                }
+            };
+
+            for gone in interfaces.iter().filter(|i| table.get(i.index).is_none()) {
+                self.socket.set_source_address(gone.index, None);
+            }
+            for interface in table.iter() {
+                self.socket
+                    .set_source_address(interface.index, interface.primary_ipv4());
+            }
+
+            *interfaces = table;
+        } else {
+            interfaces.apply(event);
+
+            match event {
+                InterfaceEvent::AddressAdded { index, .. }
+                | InterfaceEvent::AddressRemoved { index, .. } => self.socket.set_source_address(
+                    *index,
+                    interfaces.get(*index).and_then(|i| i.primary_ipv4()),
+                ),
+                InterfaceEvent::LinkRemoved { index } => {
+                    self.socket.set_source_address(*index, None)
+                }
+                _ => {}
+            }
+        }
+
+        let after = joined(&interfaces);
+
+        for (index, name) in &before {
+            if !after.contains_key(index) {
+                self.leave(*index, name);
+            }
+        }
+
+        for (index, name) in &after {
+            if !before.contains_key(index) {
+                self.join(*index, name);
+            }
+        }
+    }
+
+    fn remember_forwarded(
+        &self,
+        rule: usize,
+        packet: &DnsPacket,
+        from: &str,
+        to: &[&InterfaceInfo],
+    ) {
+        let mut names: Vec<_> = packet
+            .questions
+            .iter()
+            .map(|q| q.qname.clone())
+            .chain(packet.answers.iter().map(|rr| rr.name.clone()))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == Self::RECENT {
+            recent.pop_front();
+        }
+
+        recent.push_back(Forwarded {
+            time,
+            rule: rule + 1,
+            names,
+            from: from.to_string(),
+            to: to.iter().map(|i| i.name.clone()).collect(),
+        });
+    }
+
+    /// Answers a control request. `Request::Reload` needs the config file, which only the
+    /// owner of the daemon knows, so it is rejected here.
+    pub fn control(&self, request: Request) -> Response {
+        let config = self.config();
+
+        match request {
+            Request::Rules => {
+                let interfaces = self.interfaces.read().unwrap();
+                let disabled = self.disabled.read().unwrap();
+                let now = LocalTime::now();
+
+                let resolve = |entries: &[String]| {
+                    let mut names: Vec<_> = interfaces
+                        .iter()
+                        .filter(|i| config.matches_interface(entries, &i.name))
+                        .map(|i| i.name.clone())
+                        .collect();
+                    names.sort_unstable();
+                    names
+                };
+                let sorted = |set: &HashSet<String>| {
+                    let mut entries: Vec<_> = set.iter().cloned().collect();
+                    entries.sort_unstable();
+                    entries
+                };
+
+                let rules = config
+                    .mdns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, conf)| RuleStatus {
+                        rule: i + 1,
+                        enabled: !disabled.contains(&i),
+                        active: conf.is_active(now),
+                        sources: conf.sources.clone(),
+                        destinations: conf.destinations.clone(),
+                        source_interfaces: resolve(&conf.sources),
+                        destination_interfaces: resolve(&conf.destinations),
+                        filters: sorted(&conf.filters),
+                        deny: sorted(&conf.deny),
+                    })
+                    .collect();
+
+                Response::Rules(rules)
+            }
+
+            Request::Counters => {
+                let rule_of = |labels: &metrics::Labels| {
+                    labels
+                        .iter()
+                        .find(|(k, _)| *k == "rule")
+                        .and_then(|(_, v)| v.parse::<usize>().ok())
+                };
+
+                let mut counters: Vec<_> = (1..=config.mdns.len())
+                    .map(|rule| RuleCounters {
+                        rule,
+                        forwarded: 0,
+                        dropped: BTreeMap::new(),
+                    })
+                    .collect();
+
+                for (labels, value) in self.metrics.values(metrics::FORWARDED) {
+                    if let Some(c) = rule_of(&labels).and_then(|r| counters.get_mut(r - 1)) {
+                        c.forwarded = value;
+                    }
+                }
+
+                for (labels, value) in self.metrics.values(metrics::DROPPED) {
+                    let reason = labels.iter().find(|(k, _)| *k == "reason");
+                    if let (Some(c), Some((_, reason))) = (
+                        rule_of(&labels).and_then(|r| counters.get_mut(r - 1)),
+                        reason,
+                    ) {
+                        c.dropped.insert(reason.clone(), value);
+                    }
+                }
+
+                Response::Counters(counters)
+            }
+
+            Request::Recent => {
+                Response::Recent(self.recent.lock().unwrap().iter().cloned().collect())
+            }
+
+            Request::Reload => Response::Error("reload is not supported here".to_string()),
+
+            Request::Enable { rule } | Request::Disable { rule } => {
+                if rule == 0 || rule > config.mdns.len() {
+                    return Response::Error(format!(
+                        "rule {} does not exist, there are {} rules",
+                        rule,
+                        config.mdns.len()
+                    ));
+                }
+
+                let mut disabled = self.disabled.write().unwrap();
+                if let Request::Enable { .. } = request {
+                    disabled.remove(&(rule - 1));
+                    info!("rule {} enabled", rule);
+                } else {
+                    disabled.insert(rule - 1);
+                    info!("rule {} disabled", rule);
+                }
+
+                Response::Done
+            }
+        }
+    }
+
+    fn count_send(&self, interface: &MulticastInterface, result: &std::io::Result<usize>) {
+        let name = match interface {
+            MulticastInterface::Index(index) => {
+                let interfaces = self.interfaces.read().unwrap();
+                interfaces
+                    .name(*index as u32)
+                    .map_or_else(|| index.to_string(), str::to_string)
+            }
+            MulticastInterface::IpAddr(address) => address.to_string(),
+            MulticastInterface::Default => "default".to_string(),
+        };
+
+        match result {
+            Ok(sent) => {
+                self.metrics
+                    .add(metrics::BYTES_SENT, &[("interface", &name)], *sent as u64)
+            }
+            Err(_) => self
+                .metrics
+                .inc(metrics::SEND_ERRORS, &[("interface", &name)]),
+        }
+    }
+
+    fn join(&self, index: u32, name: &str) {
+        match self.socket.join_interface(index) {
+            Ok(()) => info!("joined multicast group on {}", name),
+            Err(e) => warn!("error in joining group on {}: {:?}", name, e),
+        }
+    }
+
+    fn leave(&self, index: u32, name: &str) {
+        match self.socket.leave_interface(index) {
+            Ok(()) => info!("left multicast group on {}", name),
+            Err(e) => trace!("error in leaving group on {}: {:?}", name, e),
+        }
+    }
+
+    /// Reads packets until the socket would block
+    fn read_packets(&self) {
         loop {
-            match self.socket.receive() {
-                Ok(msg) => self.process_packet(msg),
-                Err(e)
-                    if e.get_ref().map_or(false, |e| {
-                        e.downcast_ref::<nix::Error>()
-                            .is_some_and(|c| *c == Errno::EAGAIN)
-                    }) =>
-                {
+            match self.socket.receive_batch() {
+                Ok(msgs) => msgs.into_iter().for_each(|msg| {
+                    self.process_packet(msg);
+                }),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    warn!("error in reading from socket {:?} ", e);
+                    return;
+                }
+            };
+        }
+    }
+
+    /// Forwards a packet. Returns the decision of every rule that matched it, denials included.
+    pub fn process_packet(&self, msg: Message) -> Vec<Decision> {
+        let mut decisions = vec![];
+
+        for Forward {
+            decision,
+            data,
+            interfaces,
+        } in self.forward(&msg)
+        {
+            let results = self.socket.send_many(&data, &interfaces);
+
+            for (interface, result) in interfaces.iter().zip(results) {
+                self.count_send(interface, &result);
+                if let Err(e) = result {
+                    warn!("error in sending mdns packet to {:?}: {:?}", interface, e);
+                }
+            }
+
+            decisions.push(decision);
+        }
+
+        decisions
+    }
+
+    /// Runs the listener on the current tokio runtime. This is the async counterpart of
+    /// `Mdns::register`.
+    #[cfg(feature = "tokio")]
+    pub async fn run(&self) -> std::io::Result<()> {
+        let socket = AsyncMulticastSocket::new(self.socket.try_clone()?)?;
+        let netlink = tokio::io::unix::AsyncFd::new(self.netlink.as_raw_fd())?;
+
+        let mut schedules = tokio::time::interval(Self::SCHEDULE_INTERVAL);
+
+        info!("listener started");
+
+        loop {
+            let msgs = tokio::select! {
+                msgs = socket.receive_batch() => msgs,
+                guard = netlink.readable() => {
+                    self.read_interface_events();
+                    guard?.clear_ready();
+                    continue;
+                }
+                _ = schedules.tick() => {
+                    self.check_schedules();
                     continue;
                 }
+            };
+
+            let msgs = match msgs {
+                Ok(msgs) => msgs,
                 Err(e) => {
                     warn!("error in reading from socket {:?} ", e);
+                    continue;
                 }
             };
+
+            for msg in msgs {
+                for Forward {
+                    data, interfaces, ..
+                } in self.forward(&msg)
+                {
+                    for interface in interfaces {
+                        let result = socket.send(&data, &interface).await;
+                        self.count_send(&interface, &result);
+                        if let Err(e) = result {
+                            warn!("error in sending mdns packet to {:?}: {:?}", interface, e);
+                        }
+                    }
+                }
+            }
         }
     }
 
-    pub fn process_packet(&self, msg: crate::socket::Message) {
-        // TODO: Generalize this to parse any type of supported packet
-        let packet = DnsPacket::parse(&msg.data).unwrap_or_else(|e| {
-            trace!("{:0x?}", msg.data);
+    /// Decides which interfaces a packet should be forwarded to. Returns the decision of every
+    /// rule that matched the packet with the payload and the interfaces it should be sent out of.
+    fn forward<'a>(&self, msg: &'a Message) -> Vec<Forward<'a>> {
+        let mut out = vec![];
 
-            panic!(
-                "failed to parse packet as a dns packet. origin = {:?} interface = {:?} error = {:?}, loose_string = {:02x?}",
-                msg.origin_address,msg.interface, e, msg.data,
-            )
-        });
+        let config = self.config();
+        let interfaces = self.interfaces.read().unwrap();
 
-        let src_ifname = if let MulticastInterface::Index(idx) = msg.interface {
-            ifidx_to_ifname(idx as u32)
-        } else {
-            "lo".to_string()
+        let src_ifname = match msg.interface_index() {
+            Ok(index) => interfaces.name(index).unwrap_or_default().to_string(),
+            Err(e) => {
+                warn!("dropping packet from {:?}: {}", msg.origin_address, e);
+                self.metrics
+                    .inc(metrics::IGNORED, &[("reason", "no_interface")]);
+                return out;
+            }
+        };
+
+        self.metrics.inc(
+            metrics::RECEIVED,
+            &[("interface", &src_ifname), ("protocol", "mdns")],
+        );
+
+        if !config.uses_interface(&src_ifname) {
+            trace!("ignoring packet from unconfigured interface {}", src_ifname);
+            self.metrics
+                .inc(metrics::IGNORED, &[("reason", "unconfigured_interface")]);
+            return out;
+        }
+
+        if config.sockets.mdns.validate_ttl && msg.ttl != Some(255) {
+            trace!(
+                "ignoring packet from {:?} on {} with ttl {:?}",
+                msg.origin_address,
+                src_ifname,
+                msg.ttl
+            );
+            self.metrics.inc(metrics::IGNORED, &[("reason", "ttl")]);
+            return out;
+        }
+
+        // TODO: Generalize this to parse any type of supported packet
+        let packet = match DnsPacket::parse(&msg.data) {
+            Ok(packet) => packet,
+            Err(e) => {
+                debug!(
+                    "dropping packet from {:?} on {} that failed to parse: {}",
+                    msg.origin_address, src_ifname, e
+                );
+                trace!("{:02x?}", msg.data);
+                self.metrics.inc(
+                    metrics::PARSE_ERRORS,
+                    &[("protocol", "mdns"), ("kind", e.kind())],
+                );
+                return out;
+            }
         };
 
         trace!(
-            "EVENT src-if = {} if-index {:?} address = {:?}, packet: {:?} answers = {:?}",
+            "EVENT src-if = {} if-index {:?} address = {:?}\n{}",
             src_ifname,
             msg.interface,
             msg.origin_address,
-            packet.questions.iter().collect::<Vec<_>>(),
-            packet.answers.iter().collect::<Vec<_>>()
+            packet
         );
 
-        let interfaces = get_if_addrs::get_if_addrs().unwrap();
-        for conf in &self.config.mdns {
+        let now = LocalTime::now();
+
+        let disabled = self.disabled.read().unwrap().clone();
+
+        for (rule, conf) in config.mdns.iter().enumerate() {
+            if disabled.contains(&rule) {
+                continue;
+            }
+
             let mut dst_ifs = vec![];
+            let mut verdicts = vec![];
 
-            for query in &packet.questions {
-                if conf.destinations.contains(&src_ifname)
-                    && (conf.filters.is_empty() || conf.filters.contains(&query.qname.to_string()))
-                {
-                    dst_ifs.extend(
-                        conf.sources
-                            .iter()
-                            .filter_map(|dst_if| interfaces.iter().find(|x| &x.name == dst_if)),
-                    );
+            if !packet.questions.is_empty()
+                && config.matches_interface(&conf.destinations, &src_ifname)
+            {
+                if let Some(verdict) = check_questions(conf, &packet) {
+                    if verdict.is_allowed() {
+                        dst_ifs.extend(
+                            interfaces
+                                .iter()
+                                .filter(|i| config.matches_interface(&conf.sources, &i.name)),
+                        );
+                    }
+                    verdicts.push(verdict);
                 }
             }
 
-            for answer in &packet.answers {
-                if conf.sources.contains(&src_ifname)
-                    && (conf.filters.is_empty() || conf.filters.contains(&answer.name.to_string()))
-                {
-                    dst_ifs.extend(
-                        conf.destinations
-                            .iter()
-                            .filter_map(|dst_if| interfaces.iter().find(|x| &x.name == dst_if)),
-                    );
+            if !packet.answers.is_empty()
+                && config.matches_interface(&conf.sources, &src_ifname)
+                && txt_filters_match(conf, &packet)
+            {
+                let sender = msg.origin_address.map(|a| a.ip());
+                let verdict = if conf.allows_sender(sender, msg.source_mac) {
+                    check_answers(conf, &packet)
+                } else {
+                    Some(Verdict::SenderNotAllowed)
+                };
+
+                if let Some(verdict) = verdict {
+                    if verdict.is_allowed() {
+                        dst_ifs.extend(
+                            interfaces
+                                .iter()
+                                .filter(|i| config.matches_interface(&conf.destinations, &i.name)),
+                        );
+                    }
+                    verdicts.push(verdict);
                 }
             }
 
-            for dst_if in dst_ifs {
-                let dst_ifid = ifname_to_ifidx(dst_if.name.to_string());
+            // A denied name anywhere in the packet keeps it out of this rule
+            let verdict = match verdicts.iter().position(|v| !v.is_allowed()) {
+                Some(denied) => verdicts.swap_remove(denied),
+                None if !verdicts.is_empty() => verdicts.swap_remove(0),
+                None => continue,
+            };
+
+            let verdict = if verdict.is_allowed() && !conf.is_active(now) {
+                Verdict::OutsideSchedule
+            } else {
+                verdict
+            };
+
+            let rule_label = (rule + 1).to_string();
 
+            if !verdict.is_allowed() {
+                self.metrics.inc(
+                    metrics::DROPPED,
+                    &[("rule", &rule_label), ("reason", verdict.kind())],
+                );
+                debug!(
+                    "rule {} not forwarding packet {} from {:?} on {}: {}",
+                    rule + 1,
+                    packet.summary(),
+                    msg.origin_address,
+                    src_ifname,
+                    verdict
+                );
+
+                out.push(Forward {
+                    decision: Decision { rule, verdict },
+                    data: Cow::Borrowed(&msg.data[..]),
+                    interfaces: vec![],
+                });
+                continue;
+            }
+
+            // A glob or zone may put the ingress interface on both sides of a rule
+            dst_ifs.retain(|i| i.name != src_ifname);
+            dst_ifs.sort_by_key(|i| i.index);
+            dst_ifs.dedup_by_key(|i| i.index);
+
+            if dst_ifs.is_empty() {
+                continue;
+            }
+
+            let data = if conf.strip_edns_options {
+                match DnsPacket::strip_edns_options(&msg.data) {
+                    Ok(Some(data)) => Cow::Owned(data),
+                    Ok(None) => Cow::Borrowed(&msg.data[..]),
+                    Err(e) => {
+                        warn!("error in stripping edns options: {:?}", e);
+                        Cow::Borrowed(&msg.data[..])
+                    }
+                }
+            } else {
+                Cow::Borrowed(&msg.data[..])
+            };
+
+            if conf.send_goodbyes && !conf.schedule.is_empty() && packet.header.qr() {
+                let mut answers: Vec<_> =
+                    packet.answers.iter().map(|rr| rr.name.as_str()).collect();
+                answers.sort_unstable();
+                answers.dedup();
+
+                self.schedules
+                    .lock()
+                    .unwrap()
+                    .remember(rule, answers.join(" "), data.to_vec());
+            }
+
+            self.remember_forwarded(rule, &packet, &src_ifname, &dst_ifs);
+
+            let mut dst_indexes = vec![];
+            for dst_if in dst_ifs {
                 info!(
-                    "forwarding packet packet {:?} from {} to {}",
-                    packet, src_ifname, dst_if.name
+                    "forwarding packet {} from {} to {} (rule {}, {})",
+                    packet.summary(),
+                    src_ifname,
+                    dst_if.name,
+                    rule + 1,
+                    verdict
                 );
                 // TODO(ishan): Take a note of transaction id
                 // and avoid feedback loops
 
-                self.socket
-                    .send(&msg.data, &MulticastInterface::Index(dst_ifid as i32))
-                    .expect("error in sending mdns packet");
+                dst_indexes.push(MulticastInterface::Index(dst_if.index as i32));
             }
+
+            self.metrics
+                .inc(metrics::FORWARDED, &[("rule", &rule_label)]);
+
+            out.push(Forward {
+                decision: Decision { rule, verdict },
+                data,
+                interfaces: dst_indexes,
+            });
         }
+
+        out
     }
 }
 
-fn ifidx_to_ifname(idx: u32) -> String {
-    let out = CString::new("askdjhaskdjakdjadksa").unwrap();
+/// Decides a query. A denied question denies it, otherwise one allowed question is enough.
+fn check_questions(conf: &MdnsConfig, packet: &DnsPacket) -> Option<Verdict> {
+    let qnames = || packet.questions.iter().map(|q| q.qname.as_str());
 
-    unsafe {
-        let ptr = out.into_raw();
-        let response = libc::if_indextoname(idx, ptr);
+    if let Some(denied) = conf.denied(qnames()) {
+        return Some(denied);
+    }
 
-        CString::from_raw(response).into_string().unwrap()
+    if conf.filters.is_empty() {
+        return Some(Verdict::Allow(None));
     }
+
+    qnames().find_map(|name| conf.check_name(name))
+}
+
+/// Decides a response. Owner names and PTR targets of every record count towards a deny, so
+/// a denied service instance also stops the browse answer that points to it. An answer whose
+/// owner name is allowed, directly or through an alias, allows the response.
+fn check_answers(conf: &MdnsConfig, packet: &DnsPacket) -> Option<Verdict> {
+    let names = packet
+        .answers
+        .iter()
+        .chain(packet.additional.iter())
+        .flat_map(|rr| std::iter::once(rr.name.as_str()).chain(rr.rdata.ptr_target()));
+
+    if let Some(denied) = conf.denied(names) {
+        return Some(denied);
+    }
+
+    if conf.filters.is_empty() {
+        return Some(Verdict::Allow(None));
+    }
+
+    let allowed = follow_aliases(conf, packet);
+    packet
+        .answers
+        .iter()
+        .find_map(|answer| allowed.get(answer.name.as_str()))
+        .map(|entry| Verdict::Allow(Some(entry.clone())))
 }
 
-fn ifname_to_ifidx(name: String) -> u32 {
-    let out = name.as_ptr() as *const _;
-    unsafe { libc::if_nametoindex(out) }
+/// Returns the names in the packet that the rule allows with the entry that allowed them. The
+/// target of a CNAME record whose owner name is allowed is allowed by the same entry, and so is
+/// the name a DNAME record rewrites an allowed name below its owner to. Chains of aliases are
+/// followed.
+fn follow_aliases<'a>(conf: &MdnsConfig, packet: &'a DnsPacket) -> HashMap<&'a str, String> {
+    let records = || packet.answers.iter().chain(packet.additional.iter());
+
+    let mut names: HashMap<&str, String> = records()
+        .filter_map(|rr| match conf.check_name(&rr.name) {
+            Some(Verdict::Allow(Some(entry))) => Some((rr.name.as_str(), entry)),
+            _ => None,
+        })
+        .collect();
+
+    loop {
+        let mut found = vec![];
+
+        for rr in records() {
+            if let Some(target) = rr.rdata.alias_target() {
+                if let Some(entry) = names.get(rr.name.as_str()) {
+                    found.push((target, entry.clone()));
+                }
+            }
+
+            if let RData::Dname(dname) = &rr.rdata {
+                for (name, entry) in &names {
+                    if let Some(target) = dname.substitute(&rr.name, name) {
+                        found.extend(
+                            records()
+                                .filter(|other| other.name.eq_ignore_ascii_case(&target))
+                                .map(|other| (other.name.as_str(), entry.clone())),
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut changed = false;
+        for (name, entry) in found {
+            if !names.contains_key(name) {
+                names.insert(name, entry);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return names;
+        }
+    }
+}
+
+/// Returns true if the service instances the packet answers for have TXT records that satisfy
+/// every entry in `txt_filters`, or if the rule has no TXT filters. The instance of a PTR answer
+/// is its target and that of an SRV or TXT answer its owner name. Every instance answered has to
+/// match, since the packet is forwarded whole. Other answers, e.g. host addresses, don't count,
+/// so a response without any instance passes.
+fn txt_filters_match(conf: &MdnsConfig, packet: &DnsPacket) -> bool {
+    if conf.txt_filters.is_empty() {
+        return true;
+    }
+
+    let satisfies = |instance: &str| {
+        packet
+            .answers
+            .iter()
+            .chain(packet.additional.iter())
+            .filter(|rr| rr.name.eq_ignore_ascii_case(instance))
+            .any(|rr| match &rr.rdata {
+                RData::Txt(txt) => conf.txt_filters.iter().all(|(key, expected)| {
+                    txt.get(key).is_some_and(|attr| {
+                        expected.is_empty()
+                            || attr
+                                .value_str()
+                                .is_some_and(|v| v.split(',').any(|v| v.trim() == expected))
+                    })
+                }),
+                _ => false,
+            })
+    };
+
+    packet
+        .answers
+        .iter()
+        .filter_map(|rr| match &rr.rdata {
+            RData::Ptr(_) => rr.rdata.ptr_target(),
+            RData::Srv(_) | RData::Txt(_) => Some(rr.name.as_str()),
+            _ => None,
+        })
+        .all(satisfies)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rule(extra: &str) -> MdnsConfig {
+        toml::from_str(&format!(
+            "sources = [\"eth0\"]\ndestinations = [\"eth1\"]\n{}",
+            extra
+        ))
+        .unwrap()
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut out = vec![];
+        for label in name.split('.') {
+            out.push(label.len() as u8);
+            out.extend_from_slice(label.as_bytes());
+        }
+        out.push(0);
+        out
+    }
+
+    fn record(owner: &str, rtype: u16, rdata: &[u8]) -> Vec<u8> {
+        let mut out = name(owner);
+        out.extend_from_slice(&rtype.to_be_bytes());
+        out.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x78]);
+        out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        out.extend_from_slice(rdata);
+        out
+    }
+
+    fn ptr(owner: &str, target: &str) -> Vec<u8> {
+        record(owner, 12, &name(target))
+    }
+
+    fn txt(owner: &str, strings: &[&str]) -> Vec<u8> {
+        let mut rdata = vec![];
+        for s in strings {
+            rdata.push(s.len() as u8);
+            rdata.extend_from_slice(s.as_bytes());
+        }
+        record(owner, 16, &rdata)
+    }
+
+    fn a(owner: &str) -> Vec<u8> {
+        record(owner, 1, &[192, 0, 2, 5])
+    }
+
+    fn response(answers: &[Vec<u8>]) -> DnsPacket {
+        let mut data = vec![0x00, 0x00, 0x84, 0x00, 0x00, 0x00];
+        data.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        data.extend(answers.concat());
+        DnsPacket::parse(&data).unwrap()
+    }
+
+    #[test]
+    fn txt_filters_match_instances() {
+        let conf = rule("[txt_filters]\npdl = \"application/pdf\"");
+
+        // Host address answers to re-queries have no instance to check
+        assert!(txt_filters_match(&conf, &response(&[a("printer.local")])));
+
+        let pdf = txt(
+            "Printer._ipp._tcp.local",
+            &["ty=Printer", "pdl=application/postscript,application/pdf"],
+        );
+        let postscript = txt("Other._ipp._tcp.local", &["pdl=application/postscript"]);
+
+        assert!(txt_filters_match(
+            &conf,
+            &response(&[
+                ptr("_ipp._tcp.local", "Printer._ipp._tcp.local"),
+                pdf.clone()
+            ])
+        ));
+        assert!(!txt_filters_match(
+            &conf,
+            &response(&[
+                ptr("_ipp._tcp.local", "Other._ipp._tcp.local"),
+                postscript.clone()
+            ])
+        ));
+
+        // A PTR answer without the TXT record of its instance doesn't match
+        assert!(!txt_filters_match(
+            &conf,
+            &response(&[ptr("_ipp._tcp.local", "Printer._ipp._tcp.local")])
+        ));
+
+        // Every instance has to match, not just one of them
+        assert!(!txt_filters_match(
+            &conf,
+            &response(&[
+                ptr("_ipp._tcp.local", "Printer._ipp._tcp.local"),
+                ptr("_ipp._tcp.local", "Other._ipp._tcp.local"),
+                pdf,
+                postscript
+            ])
+        ));
+
+        // An empty value only needs the key
+        let conf = rule("[txt_filters]\nty = \"\"");
+        assert!(txt_filters_match(
+            &conf,
+            &response(&[txt("Printer._ipp._tcp.local", &["ty=Printer"])])
+        ));
+        assert!(!txt_filters_match(
+            &conf,
+            &response(&[txt("Printer._ipp._tcp.local", &["pdl=application/pdf"])])
+        ));
+    }
+
+    #[test]
+    fn check_answers_by_name() {
+        let conf = rule("filters = [\"*._ipp._tcp.local\", \"printer.local\"]");
+
+        assert_eq!(
+            check_answers(&conf, &response(&[a("printer.local")])),
+            Some(Verdict::Allow(Some("printer.local".to_string())))
+        );
+        assert_eq!(check_answers(&conf, &response(&[a("nas.local")])), None);
+        assert_eq!(
+            check_answers(
+                &conf,
+                &response(&[txt("Printer._ipp._tcp.local", &["ty=Printer"])])
+            ),
+            Some(Verdict::Allow(Some("*._ipp._tcp.local".to_string())))
+        );
+
+        // A denied instance also stops the browse answer that points to it
+        let conf = rule("filters = [\"_ipp._tcp.local\"]\ndeny = [\"Other._ipp._tcp.local\"]");
+        assert_eq!(
+            check_answers(
+                &conf,
+                &response(&[
+                    ptr("_ipp._tcp.local", "Printer._ipp._tcp.local"),
+                    ptr("_ipp._tcp.local", "Other._ipp._tcp.local")
+                ])
+            ),
+            Some(Verdict::Deny("Other._ipp._tcp.local".to_string()))
+        );
+        assert_eq!(
+            check_answers(
+                &conf,
+                &response(&[ptr("_ipp._tcp.local", "Printer._ipp._tcp.local")])
+            ),
+            Some(Verdict::Allow(Some("_ipp._tcp.local".to_string())))
+        );
+
+        let conf = rule("deny = [\"nas.local\"]");
+        assert_eq!(
+            check_answers(&conf, &response(&[a("printer.local")])),
+            Some(Verdict::Allow(None))
+        );
+    }
 }
@@ -0,0 +1,71 @@
+// Rules with a `schedule` only forward while one of their windows is open. With `send_goodbyes`
+// the latest response forwarded for each set of answer names is kept, so that a goodbye for it
+// can be sent when the window closes.
+use crate::{Config, LocalTime};
+use log::debug;
+use std::collections::HashMap;
+
+/// Responses kept per rule. Anything past this gets no goodbye.
+const MAX_RESPONSES: usize = 256;
+
+#[derive(Debug, Default)]
+pub(super) struct Schedules {
+    rules: Vec<RuleState>,
+}
+
+#[derive(Debug, Default)]
+struct RuleState {
+    /// `None` until the first check after startup or a reload
+    active: Option<bool>,
+    /// Forwarded responses by their answer names
+    responses: HashMap<String, Vec<u8>>,
+}
+
+impl Schedules {
+    /// Forgets every rule. Rule numbers change when the config is reloaded.
+    pub(super) fn reset(&mut self) {
+        self.rules.clear();
+    }
+
+    /// Keeps a response forwarded by `rule`, replacing an earlier one with the same answers
+    pub(super) fn remember(&mut self, rule: usize, answers: String, data: Vec<u8>) {
+        if self.rules.len() <= rule {
+            self.rules.resize_with(rule + 1, RuleState::default);
+        }
+
+        let responses = &mut self.rules[rule].responses;
+        if responses.len() >= MAX_RESPONSES && !responses.contains_key(&answers) {
+            debug!(
+                "rule {} keeps too many responses, no goodbye for {}",
+                rule + 1,
+                answers
+            );
+            return;
+        }
+
+        responses.insert(answers, data);
+    }
+
+    /// Returns the rules whose window closed since the last call along with the responses
+    /// they forwarded while it was open
+    pub(super) fn update(&mut self, config: &Config, now: LocalTime) -> Vec<(usize, Vec<Vec<u8>>)> {
+        self.rules
+            .resize_with(config.mdns.len().max(self.rules.len()), RuleState::default);
+
+        let mut closed = vec![];
+
+        for (rule, conf) in config.mdns.iter().enumerate() {
+            let state = &mut self.rules[rule];
+            let active = conf.is_active(now);
+
+            if state.active == Some(true) && !active {
+                let responses = state.responses.drain().map(|(_, data)| data).collect();
+                closed.push((rule, responses));
+            }
+
+            state.active = Some(active);
+        }
+
+        closed
+    }
+}
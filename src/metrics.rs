@@ -0,0 +1,217 @@
+// Counters exported over HTTP in the Prometheus text format. The forwarder updates them as it
+// processes packets and `serve` answers scrapes from its own thread, so a slow scraper never
+// holds up forwarding.
+use log::{debug, info, warn};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+pub const RECEIVED: &str = "multicaster_packets_received_total";
+pub const IGNORED: &str = "multicaster_packets_ignored_total";
+pub const PARSE_ERRORS: &str = "multicaster_parse_errors_total";
+pub const FORWARDED: &str = "multicaster_packets_forwarded_total";
+pub const DROPPED: &str = "multicaster_packets_dropped_total";
+pub const BYTES_SENT: &str = "multicaster_bytes_sent_total";
+pub const SEND_ERRORS: &str = "multicaster_send_errors_total";
+
+const HELP: [(&str, &str); 7] = [
+    (
+        RECEIVED,
+        "Packets received per ingress interface and protocol",
+    ),
+    (
+        IGNORED,
+        "Packets dropped before any rule was checked, per reason",
+    ),
+    (PARSE_ERRORS, "Packets that failed to parse, per error kind"),
+    (FORWARDED, "Packets forwarded per rule"),
+    (
+        DROPPED,
+        "Packets a rule matched but didn't forward, per reason",
+    ),
+    (BYTES_SENT, "Bytes sent per egress interface"),
+    (SEND_ERRORS, "Failed sends per egress interface"),
+];
+
+pub type Labels = Vec<(&'static str, String)>;
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<(&'static str, Labels), u64>>,
+}
+
+impl Metrics {
+    pub fn add(&self, name: &'static str, labels: &[(&'static str, &str)], value: u64) {
+        let labels = labels.iter().map(|&(k, v)| (k, v.to_string())).collect();
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .entry((name, labels))
+            .or_default() += value;
+    }
+
+    pub fn inc(&self, name: &'static str, labels: &[(&'static str, &str)]) {
+        self.add(name, labels, 1);
+    }
+
+    /// Current value of a counter. Counters that were never updated are 0.
+    pub fn get(&self, name: &'static str, labels: &[(&'static str, &str)]) -> u64 {
+        let labels: Labels = labels.iter().map(|&(k, v)| (k, v.to_string())).collect();
+        self.counters
+            .lock()
+            .unwrap()
+            .get(&(name, labels))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Drops every counter that has a label named `label`, e.g. the per-rule counters once the
+    /// rules have been renumbered
+    pub fn remove_labelled(&self, label: &str) {
+        self.counters
+            .lock()
+            .unwrap()
+            .retain(|(_, labels), _| labels.iter().all(|(k, _)| *k != label));
+    }
+
+    /// Every counter named `name` with its labels
+    pub fn values(&self, name: &'static str) -> Vec<(Labels, u64)> {
+        self.counters
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((n, _), _)| *n == name)
+            .map(|((_, labels), value)| (labels.clone(), *value))
+            .collect()
+    }
+
+    /// Every counter in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let counters = self.counters.lock().unwrap();
+        let mut out = String::new();
+        let mut last = None;
+
+        for ((name, labels), value) in counters.iter() {
+            if last != Some(name) {
+                let help = HELP.iter().find(|(n, _)| n == name).map_or("", |(_, h)| h);
+                let _ = writeln!(out, "# HELP {} {}", name, help);
+                let _ = writeln!(out, "# TYPE {} counter", name);
+                last = Some(name);
+            }
+
+            let labels: Vec<_> = labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+                .collect();
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels.join(","), value);
+        }
+
+        out
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serves `GET /metrics` on `address` from a new thread. Signals should be blocked before this
+/// is called so the thread inherits the mask, see `Reactor::add_signals`.
+pub fn serve(metrics: Arc<Metrics>, address: SocketAddr) -> io::Result<thread::JoinHandle<()>> {
+    let listener = TcpListener::bind(address)?;
+    info!("serving metrics on http://{}/metrics", address);
+
+    thread::Builder::new()
+        .name("metrics".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(e) = respond(&metrics, stream) {
+                            debug!("error in answering metrics request: {}", e);
+                        }
+                    }
+                    Err(e) => warn!("error in accepting metrics connection: {}", e),
+                }
+            }
+        })
+}
+
+fn respond(metrics: &Metrics, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(2)))?;
+
+    // Only the request line matters. Headers are read so the client doesn't see a reset.
+    let mut request = vec![];
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+        (Some("GET"), _) => ("404 Not Found", "not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "method not allowed\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_counters() {
+        let metrics = Metrics::default();
+        metrics.inc(RECEIVED, &[("interface", "eth0"), ("protocol", "mdns")]);
+        metrics.inc(RECEIVED, &[("interface", "eth0"), ("protocol", "mdns")]);
+        metrics.add(BYTES_SENT, &[("interface", "eth1")], 120);
+
+        assert_eq!(
+            metrics.get(RECEIVED, &[("interface", "eth0"), ("protocol", "mdns")]),
+            2
+        );
+        assert_eq!(metrics.get(SEND_ERRORS, &[("interface", "eth1")]), 0);
+
+        metrics.inc(FORWARDED, &[("rule", "2")]);
+        metrics.remove_labelled("rule");
+        assert_eq!(metrics.get(FORWARDED, &[("rule", "2")]), 0);
+        metrics.inc(DROPPED, &[("rule", "1"), ("reason", "de\"nied")]);
+        assert_eq!(
+            metrics.render(),
+            "\
+# HELP multicaster_bytes_sent_total Bytes sent per egress interface
+# TYPE multicaster_bytes_sent_total counter
+multicaster_bytes_sent_total{interface=\"eth1\"} 120
+# HELP multicaster_packets_dropped_total Packets a rule matched but didn't forward, per reason
+# TYPE multicaster_packets_dropped_total counter
+multicaster_packets_dropped_total{rule=\"1\",reason=\"de\\\"nied\"} 1
+# HELP multicaster_packets_received_total Packets received per ingress interface and protocol
+# TYPE multicaster_packets_received_total counter
+multicaster_packets_received_total{interface=\"eth0\",protocol=\"mdns\"} 2
+"
+        );
+    }
+}
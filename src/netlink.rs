@@ -0,0 +1,253 @@
+// rtnetlink subscription used to track interfaces and addresses as they come and go.
+// Checkout the [rtnetlink manpage](https://man7.org/linux/man-pages/man7/rtnetlink.7.html)
+use nix::{
+    errno::Errno,
+    sys::socket::{
+        self as sock, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol, SockType,
+    },
+    unistd,
+};
+use std::{
+    io::{self, Result as IoResult},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    os::unix::io::{AsRawFd, RawFd},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceEvent {
+    LinkAdded {
+        index: u32,
+        name: String,
+    },
+    LinkRemoved {
+        index: u32,
+    },
+    AddressAdded {
+        index: u32,
+        address: IpAddr,
+    },
+    AddressRemoved {
+        index: u32,
+        address: IpAddr,
+    },
+    /// The kernel dropped messages because we didn't read fast enough. The interface table has
+    /// to be loaded again.
+    Overrun,
+}
+
+#[derive(Debug)]
+pub struct NetlinkSocket {
+    fd: RawFd,
+}
+
+impl NetlinkSocket {
+    const BUFFER_SIZE: usize = 8192;
+
+    /// Opens a non blocking socket subscribed to link and address changes
+    pub fn new() -> IoResult<Self> {
+        let fd = sock::socket(
+            AddressFamily::Netlink,
+            SockType::Raw,
+            SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkRoute,
+        )
+        .map_err(io::Error::other)?;
+
+        let groups = libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR;
+
+        if let Err(e) = sock::bind(fd, &NetlinkAddr::new(0, groups as u32)) {
+            let _ = unistd::close(fd);
+            return Err(io::Error::other(e));
+        }
+
+        Ok(Self { fd })
+    }
+
+    /// Reads every pending message until the socket would block
+    pub fn read_events(&self) -> IoResult<Vec<InterfaceEvent>> {
+        let mut buf = vec![0; Self::BUFFER_SIZE];
+        let mut events = vec![];
+
+        loop {
+            match sock::recv(self.fd, &mut buf, MsgFlags::empty()) {
+                Ok(n) => events.extend(parse_messages(&buf[..n])),
+                Err(Errno::EAGAIN) => return Ok(events),
+                Err(Errno::ENOBUFS) => events.push(InterfaceEvent::Overrun),
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(io::Error::other(e)),
+            }
+        }
+    }
+}
+
+impl AsRawFd for NetlinkSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for NetlinkSocket {
+    fn drop(&mut self) {
+        let _ = unistd::close(self.fd);
+    }
+}
+
+const NLMSG_HDRLEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+const IFADDRMSG_LEN: usize = 8;
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_u16(data: &[u8]) -> u16 {
+    u16::from_ne_bytes([data[0], data[1]])
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_ne_bytes([data[0], data[1], data[2], data[3]])
+}
+
+/// Iterates over the `rtattr`s in `data` as (type, payload) pairs
+fn attributes(mut data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if data.len() < 4 {
+            return None;
+        }
+
+        let len = read_u16(data) as usize;
+        if len < 4 || len > data.len() {
+            return None;
+        }
+
+        let attr = (read_u16(&data[2..]), &data[4..len]);
+        data = &data[align(len).min(data.len())..];
+
+        Some(attr)
+    })
+}
+
+/// Parses a buffer of netlink messages. Messages we don't care about are skipped.
+pub fn parse_messages(mut data: &[u8]) -> Vec<InterfaceEvent> {
+    let mut events = vec![];
+
+    while data.len() >= NLMSG_HDRLEN {
+        let len = read_u32(data) as usize;
+        if len < NLMSG_HDRLEN || len > data.len() {
+            break;
+        }
+
+        let msg_type = read_u16(&data[4..]);
+        let payload = &data[NLMSG_HDRLEN..len];
+
+        match msg_type {
+            libc::RTM_NEWLINK | libc::RTM_DELLINK if payload.len() >= IFINFOMSG_LEN => {
+                let index = read_u32(&payload[4..]);
+
+                if msg_type == libc::RTM_DELLINK {
+                    events.push(InterfaceEvent::LinkRemoved { index });
+                } else if let Some((_, name)) =
+                    attributes(&payload[IFINFOMSG_LEN..]).find(|(t, _)| *t == libc::IFLA_IFNAME)
+                {
+                    let name = name.split(|&c| c == 0).next().unwrap_or_default();
+
+                    events.push(InterfaceEvent::LinkAdded {
+                        index,
+                        name: String::from_utf8_lossy(name).into_owned(),
+                    });
+                }
+            }
+
+            libc::RTM_NEWADDR | libc::RTM_DELADDR if payload.len() >= IFADDRMSG_LEN => {
+                let family = payload[0] as i32;
+                let index = read_u32(&payload[4..]);
+
+                // For IPv4 IFA_LOCAL is the address of the interface, IFA_ADDRESS is the peer
+                // address on point to point links. IPv6 only sends IFA_ADDRESS.
+                let mut address = None;
+                for (t, value) in attributes(&payload[IFADDRMSG_LEN..]) {
+                    let parsed = match (family, value.len()) {
+                        (libc::AF_INET, 4) => {
+                            IpAddr::V4(Ipv4Addr::new(value[0], value[1], value[2], value[3]))
+                        }
+                        (libc::AF_INET6, 16) => {
+                            IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(value).unwrap()))
+                        }
+                        _ => continue,
+                    };
+
+                    if t == libc::IFA_LOCAL || (t == libc::IFA_ADDRESS && address.is_none()) {
+                        address = Some(parsed);
+                    }
+                }
+
+                if let Some(address) = address {
+                    events.push(if msg_type == libc::RTM_NEWADDR {
+                        InterfaceEvent::AddressAdded { index, address }
+                    } else {
+                        InterfaceEvent::AddressRemoved { index, address }
+                    });
+                }
+            }
+
+            _ => {}
+        }
+
+        data = &data[align(len).min(data.len())..];
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_link_and_address_messages() {
+        let mut data = vec![];
+
+        // RTM_NEWLINK for index 7 named "vlan30"
+        let mut link = vec![0; IFINFOMSG_LEN];
+        link[4..8].copy_from_slice(&7u32.to_ne_bytes());
+        link.extend_from_slice(&11u16.to_ne_bytes());
+        link.extend_from_slice(&libc::IFLA_IFNAME.to_ne_bytes());
+        link.extend_from_slice(b"vlan30\0\0");
+        push_message(&mut data, libc::RTM_NEWLINK, &link);
+
+        // RTM_NEWADDR for 10.0.30.1 on index 7
+        let mut addr = vec![libc::AF_INET as u8, 24, 0, 0];
+        addr.extend_from_slice(&7u32.to_ne_bytes());
+        addr.extend_from_slice(&8u16.to_ne_bytes());
+        addr.extend_from_slice(&libc::IFA_LOCAL.to_ne_bytes());
+        addr.extend_from_slice(&[10, 0, 30, 1]);
+        push_message(&mut data, libc::RTM_DELADDR, &addr);
+
+        assert_eq!(
+            parse_messages(&data),
+            vec![
+                InterfaceEvent::LinkAdded {
+                    index: 7,
+                    name: "vlan30".to_string()
+                },
+                InterfaceEvent::AddressRemoved {
+                    index: 7,
+                    address: IpAddr::V4(Ipv4Addr::new(10, 0, 30, 1))
+                },
+            ]
+        );
+
+        // Truncated messages are ignored
+        assert!(parse_messages(&data[..20]).is_empty());
+    }
+
+    fn push_message(data: &mut Vec<u8>, msg_type: u16, payload: &[u8]) {
+        let len = NLMSG_HDRLEN + payload.len();
+
+        data.extend_from_slice(&(len as u32).to_ne_bytes());
+        data.extend_from_slice(&msg_type.to_ne_bytes());
+        data.extend_from_slice(&[0; 10]);
+        data.extend_from_slice(payload);
+        data.resize(align(data.len()), 0);
+    }
+}
@@ -8,9 +8,29 @@ pub enum ParserError {
     #[error("Unexpected end of packet")]
     UnexpectedEOP,
 
+    #[error("Compression pointer to {0} doesn't point back")]
+    InvalidPointer(usize),
+
     #[error("Label is not UTF-8")]
     LabelIsNotUTF8,
 
     #[error("Unknown rtype: {0}")]
     UnknownRType(u16),
+
+    #[error("Invalid SvcParam value for key: {0}")]
+    InvalidSvcParam(u16),
+}
+
+impl ParserError {
+    /// Name of the variant without its data, e.g. for grouping errors in metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParserError::HeaderError(_) => "HeaderError",
+            ParserError::UnexpectedEOP => "UnexpectedEOP",
+            ParserError::InvalidPointer(_) => "InvalidPointer",
+            ParserError::LabelIsNotUTF8 => "LabelIsNotUTF8",
+            ParserError::UnknownRType(_) => "UnknownRType",
+            ParserError::InvalidSvcParam(_) => "InvalidSvcParam",
+        }
+    }
 }
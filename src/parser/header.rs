@@ -1,9 +1,123 @@
 use crate::ParserError;
+use bitflags::bitflags;
+use std::fmt;
 
-#[derive(Debug, Default)]
+bitflags! {
+    /// Single bit flags in the second 16 bits of the header. RFC 1035 Section 4.1.1 & RFC 4035
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+    pub struct Flags: u16 {
+        const QR = 1 << 15;
+        const AA = 1 << 10;
+        const TC = 1 << 9;
+        const RD = 1 << 8;
+        const RA = 1 << 7;
+        const Z = 1 << 6;
+        const AD = 1 << 5;
+        const CD = 1 << 4;
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Opcode {
+    #[default]
+    Query,
+    IQuery,
+    Status,
+    Notify,
+    Update,
+    Dso,
+    Unknown(u8),
+}
+
+impl From<u8> for Opcode {
+    fn from(v: u8) -> Self {
+        match v & 0b1111 {
+            0 => Opcode::Query,
+            1 => Opcode::IQuery,
+            2 => Opcode::Status,
+            4 => Opcode::Notify,
+            5 => Opcode::Update,
+            6 => Opcode::Dso,
+            v => Opcode::Unknown(v),
+        }
+    }
+}
+
+impl From<Opcode> for u8 {
+    fn from(v: Opcode) -> Self {
+        match v {
+            Opcode::Query => 0,
+            Opcode::IQuery => 1,
+            Opcode::Status => 2,
+            Opcode::Notify => 4,
+            Opcode::Update => 5,
+            Opcode::Dso => 6,
+            Opcode::Unknown(v) => v & 0b1111,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Rcode {
+    #[default]
+    NoError,
+    FormErr,
+    ServFail,
+    NxDomain,
+    NotImp,
+    Refused,
+    YxDomain,
+    YxRrSet,
+    NxRrSet,
+    NotAuth,
+    NotZone,
+    Unknown(u8),
+}
+
+impl From<u8> for Rcode {
+    fn from(v: u8) -> Self {
+        match v & 0b1111 {
+            0 => Rcode::NoError,
+            1 => Rcode::FormErr,
+            2 => Rcode::ServFail,
+            3 => Rcode::NxDomain,
+            4 => Rcode::NotImp,
+            5 => Rcode::Refused,
+            6 => Rcode::YxDomain,
+            7 => Rcode::YxRrSet,
+            8 => Rcode::NxRrSet,
+            9 => Rcode::NotAuth,
+            10 => Rcode::NotZone,
+            v => Rcode::Unknown(v),
+        }
+    }
+}
+
+impl From<Rcode> for u8 {
+    fn from(v: Rcode) -> Self {
+        match v {
+            Rcode::NoError => 0,
+            Rcode::FormErr => 1,
+            Rcode::ServFail => 2,
+            Rcode::NxDomain => 3,
+            Rcode::NotImp => 4,
+            Rcode::Refused => 5,
+            Rcode::YxDomain => 6,
+            Rcode::YxRrSet => 7,
+            Rcode::NxRrSet => 8,
+            Rcode::NotAuth => 9,
+            Rcode::NotZone => 10,
+            Rcode::Unknown(v) => v & 0b1111,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Header {
     pub id: u16,
-    pub fields: u16,
+    flags: Flags,
+    opcode: Opcode,
+    rcode: Rcode,
     pub qd_count: u16,
     pub an_count: u16,
     pub ns_count: u16,
@@ -12,15 +126,19 @@ pub struct Header {
 
 impl Header {
     pub fn parse(data: &[u8]) -> Result<Self, ParserError> {
-        if data.len() < 12 {
+        if data.len() < Self::size() {
             return Err(ParserError::HeaderError(
-                "input length is less than 20 bytes",
+                "input length is less than 12 bytes",
             ));
         }
 
+        let fields = u16::from_be_bytes([data[2], data[3]]);
+
         Ok(Header {
             id: u16::from_be_bytes([data[0], data[1]]),
-            fields: u16::from_be_bytes([data[2], data[3]]),
+            flags: Flags::from_bits_truncate(fields),
+            opcode: Opcode::from(((fields >> 11) & 0b1111) as u8),
+            rcode: Rcode::from((fields & 0b1111) as u8),
             qd_count: u16::from_be_bytes([data[4], data[5]]),
             an_count: u16::from_be_bytes([data[6], data[7]]),
             ns_count: u16::from_be_bytes([data[8], data[9]]),
@@ -28,39 +146,202 @@ impl Header {
         })
     }
 
-    pub fn qr(&self) -> bool {
-        ((self.fields >> 15) & 1) == 1
+    /// Writes the header in to the first 12 bytes of `data`. This can be used to update the
+    /// header of an existing packet in place.
+    pub fn write(&self, data: &mut [u8]) -> Result<(), ParserError> {
+        if data.len() < Self::size() {
+            return Err(ParserError::HeaderError(
+                "output length is less than 12 bytes",
+            ));
+        }
+
+        data[0..2].copy_from_slice(&self.id.to_be_bytes());
+        data[2..4].copy_from_slice(&self.fields().to_be_bytes());
+        data[4..6].copy_from_slice(&self.qd_count.to_be_bytes());
+        data[6..8].copy_from_slice(&self.an_count.to_be_bytes());
+        data[8..10].copy_from_slice(&self.ns_count.to_be_bytes());
+        data[10..12].copy_from_slice(&self.ar_count.to_be_bytes());
+
+        Ok(())
+    }
+
+    pub fn builder() -> HeaderBuilder {
+        HeaderBuilder::default()
+    }
+
+    /// Raw value of the second 16 bits of the header
+    pub fn fields(&self) -> u16 {
+        self.flags.bits() | ((u8::from(self.opcode) as u16) << 11) | u8::from(self.rcode) as u16
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.flags = flags;
+    }
+
+    pub fn set_flag(&mut self, flag: Flags, value: bool) {
+        self.flags.set(flag, value);
+    }
+
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    pub fn set_opcode(&mut self, opcode: Opcode) {
+        self.opcode = opcode;
     }
 
-    pub fn opcode(&self) -> u8 {
-        ((self.fields >> 11) & 0b01111) as u8
+    pub fn rcode(&self) -> Rcode {
+        self.rcode
+    }
+
+    pub fn set_rcode(&mut self, rcode: Rcode) {
+        self.rcode = rcode;
+    }
+
+    pub fn qr(&self) -> bool {
+        self.flags.contains(Flags::QR)
     }
 
     pub fn aa(&self) -> bool {
-        ((self.fields >> 10) & 1) == 1
+        self.flags.contains(Flags::AA)
     }
 
     pub fn tc(&self) -> bool {
-        ((self.fields >> 9) & 1) == 1
+        self.flags.contains(Flags::TC)
     }
 
     pub fn rd(&self) -> bool {
-        ((self.fields >> 8) & 1) == 1
+        self.flags.contains(Flags::RD)
     }
 
     pub fn ra(&self) -> bool {
-        ((self.fields >> 7) & 1) == 1
+        self.flags.contains(Flags::RA)
     }
 
+    /// The three bits between RA and RCODE. These are Z, AD and CD in RFC 4035
     pub fn z(&self) -> u8 {
-        ((self.fields >> 4) & 0b111) as u8
-    }
-
-    pub fn rcode(&self) -> u8 {
-        (self.fields & 0b111) as u8
+        ((self.flags & (Flags::Z | Flags::AD | Flags::CD)).bits() >> 4) as u8
     }
 
     pub fn size() -> usize {
         12
     }
 }
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opcode::Query => write!(f, "QUERY"),
+            Opcode::IQuery => write!(f, "IQUERY"),
+            Opcode::Status => write!(f, "STATUS"),
+            Opcode::Notify => write!(f, "NOTIFY"),
+            Opcode::Update => write!(f, "UPDATE"),
+            Opcode::Dso => write!(f, "DSO"),
+            Opcode::Unknown(v) => write!(f, "OPCODE{}", v),
+        }
+    }
+}
+
+impl fmt::Display for Rcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rcode::NoError => write!(f, "NOERROR"),
+            Rcode::FormErr => write!(f, "FORMERR"),
+            Rcode::ServFail => write!(f, "SERVFAIL"),
+            Rcode::NxDomain => write!(f, "NXDOMAIN"),
+            Rcode::NotImp => write!(f, "NOTIMP"),
+            Rcode::Refused => write!(f, "REFUSED"),
+            Rcode::YxDomain => write!(f, "YXDOMAIN"),
+            Rcode::YxRrSet => write!(f, "YXRRSET"),
+            Rcode::NxRrSet => write!(f, "NXRRSET"),
+            Rcode::NotAuth => write!(f, "NOTAUTH"),
+            Rcode::NotZone => write!(f, "NOTZONE"),
+            Rcode::Unknown(v) => write!(f, "RCODE{}", v),
+        }
+    }
+}
+
+/// Lower case flag names separated by spaces, e.g. `qr aa`
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, _)) in self.iter_names().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", name.to_ascii_lowercase())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The two header lines printed by dig
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            ";; opcode: {}, status: {}, id: {}",
+            self.opcode, self.rcode, self.id
+        )?;
+        write!(
+            f,
+            ";; flags: {}; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            self.flags, self.qd_count, self.an_count, self.ns_count, self.ar_count
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HeaderBuilder {
+    header: Header,
+}
+
+impl HeaderBuilder {
+    pub fn id(mut self, id: u16) -> Self {
+        self.header.id = id;
+        self
+    }
+
+    pub fn flags(mut self, flags: Flags) -> Self {
+        self.header.flags = flags;
+        self
+    }
+
+    pub fn opcode(mut self, opcode: Opcode) -> Self {
+        self.header.opcode = opcode;
+        self
+    }
+
+    pub fn rcode(mut self, rcode: Rcode) -> Self {
+        self.header.rcode = rcode;
+        self
+    }
+
+    pub fn qd_count(mut self, count: u16) -> Self {
+        self.header.qd_count = count;
+        self
+    }
+
+    pub fn an_count(mut self, count: u16) -> Self {
+        self.header.an_count = count;
+        self
+    }
+
+    pub fn ns_count(mut self, count: u16) -> Self {
+        self.header.ns_count = count;
+        self
+    }
+
+    pub fn ar_count(mut self, count: u16) -> Self {
+        self.header.ar_count = count;
+        self
+    }
+
+    pub fn build(self) -> Header {
+        self.header
+    }
+}
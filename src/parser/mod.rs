@@ -1,6 +1,7 @@
 #![allow(unused)]
 
 mod header;
+use std::fmt;
 use std::task::Wake;
 
 pub use header::*;
@@ -41,6 +42,78 @@ impl DnsPacket {
         })
     }
 
+    /// Returns a copy of `data` with every EDNS0 option removed from the OPT record. The OPT
+    /// record itself is kept. Returns `None` if the packet does not have an OPT record.
+    pub fn strip_edns_options(data: &[u8]) -> Result<Option<Vec<u8>>, ParserError> {
+        let header = Header::parse(data)?;
+        let (_, mut offset) = DnsPacket::parse_questions(&header, data)?;
+        let records =
+            header.an_count as usize + header.ns_count as usize + header.ar_count as usize;
+
+        for _ in 0..records {
+            let (rr, read) = ResourceRecord::parse(&data[offset..], data)?;
+            offset += read;
+
+            if let RData::Opt(_) = rr.rdata {
+                let rdata_start = offset - rr.rdlength as usize;
+
+                let mut out = Vec::with_capacity(data.len());
+                out.extend_from_slice(&data[..rdata_start - 2]);
+                out.extend_from_slice(&0u16.to_be_bytes());
+                out.extend_from_slice(&data[offset..]);
+
+                return Ok(Some(out));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns a response with the PTR answers of `data`, every one with a TTL of 0. Sent this
+    /// tells caches to drop the shared records that point to the service instances. Unique
+    /// records, like the host's addresses or an instance's SRV, are left out since other
+    /// services may still rely on them. Returns `None` if there are no PTR answers. RFC 6762
+    /// Section 10.1
+    pub fn goodbye(data: &[u8]) -> Result<Option<Vec<u8>>, ParserError> {
+        let mut header = Header::parse(data)?;
+        let (_, mut offset) = DnsPacket::parse_questions(&header, data)?;
+        let mut out = vec![0; Header::size()];
+        let mut count = 0;
+
+        for _ in 0..header.an_count {
+            let (rr, read) = ResourceRecord::parse(&data[offset..], data)?;
+
+            if let RData::Ptr(_) = rr.rdata {
+                // TYPE, CLASS, TTL and RDLENGTH come right before the RDATA. Names are copied
+                // without compression since the records they pointed into are gone.
+                let rdata = offset + read - rr.rdlength as usize;
+                let mut target = vec![];
+                Qname::copy(&data[rdata..], data, &mut target)?;
+
+                Qname::copy(&data[offset..], data, &mut out)?;
+                out.extend_from_slice(&data[rdata - 10..rdata - 6]);
+                out.extend_from_slice(&0u32.to_be_bytes());
+                out.extend_from_slice(&(target.len() as u16).to_be_bytes());
+                out.extend_from_slice(&target);
+                count += 1;
+            }
+
+            offset += read;
+        }
+
+        if count == 0 {
+            return Ok(None);
+        }
+
+        header.qd_count = 0;
+        header.an_count = count;
+        header.ns_count = 0;
+        header.ar_count = 0;
+        header.write(&mut out)?;
+
+        Ok(Some(out))
+    }
+
     fn parse_additional(
         header: &Header,
         data: &[u8],
@@ -109,6 +182,100 @@ impl DnsPacket {
     }
 }
 
+/// Multi line output in the same layout as dig
+impl fmt::Display for DnsPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header)?;
+
+        if !self.questions.is_empty() {
+            write!(f, "\n\n;; QUESTION SECTION:")?;
+            for q in &self.questions {
+                write!(f, "\n{}", q)?;
+            }
+        }
+
+        let sections = [
+            ("ANSWER", &self.answers),
+            ("AUTHORITY", &self.authority),
+            ("ADDITIONAL", &self.additional),
+        ];
+
+        for (name, records) in sections {
+            if records.is_empty() {
+                continue;
+            }
+
+            write!(f, "\n\n;; {} SECTION:", name)?;
+            for rr in records {
+                write!(f, "\n{}", rr)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl DnsPacket {
+    /// Single line description of the packet listing only the names and types in each section.
+    /// This is meant for log lines where the full `Display` output is too noisy.
+    pub fn summary(&self) -> Summary<'_> {
+        Summary(self)
+    }
+}
+
+pub struct Summary<'a>(&'a DnsPacket);
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let packet = self.0;
+
+        write!(
+            f,
+            "{} id={}",
+            if packet.header.qr() {
+                "response"
+            } else {
+                "query"
+            },
+            packet.header.id
+        )?;
+
+        if !packet.questions.is_empty() {
+            write!(f, " qd=[")?;
+            for (i, q) in packet.questions.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}. {}", q.qname, type_name(q.qtype))?;
+            }
+            write!(f, "]")?;
+        }
+
+        let sections = [
+            ("an", &packet.answers),
+            ("ns", &packet.authority),
+            ("ar", &packet.additional),
+        ];
+
+        for (name, records) in sections {
+            if records.is_empty() {
+                continue;
+            }
+
+            write!(f, " {}=[", name)?;
+            for (i, rr) in records.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}. {}", rr.name, rr.rtype)?;
+            }
+            write!(f, "]")?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -123,13 +290,13 @@ mod test {
 
         assert_eq!(header.id, 0);
         assert!(header.qr());
-        assert_eq!(header.opcode(), 0);
+        assert_eq!(header.opcode(), Opcode::Query);
         assert!(header.aa());
         assert!(!header.tc());
         assert!(!header.rd());
         assert!(!header.ra());
         assert_eq!(header.z(), 0);
-        assert_eq!(header.rcode(), 0);
+        assert_eq!(header.rcode(), Rcode::NoError);
 
         assert_eq!(header.qd_count, 0);
         assert_eq!(header.an_count, 5);
@@ -137,6 +304,40 @@ mod test {
         assert_eq!(header.ar_count, 3);
     }
 
+    #[test]
+    fn write_header() {
+        let mut header = Header::builder()
+            .id(0x5648)
+            .flags(Flags::QR | Flags::RD | Flags::RA)
+            .opcode(Opcode::Query)
+            .rcode(Rcode::NxDomain)
+            .qd_count(1)
+            .an_count(2)
+            .build();
+
+        let mut data = [0; 12];
+        header.write(&mut data).expect("error in writing header");
+        assert_eq!(
+            data,
+            [0x56, 0x48, 0x81, 0x83, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(
+            Header::parse(&data).expect("error in parsing header"),
+            header
+        );
+
+        // rcode uses all 4 bits
+        header.set_rcode(Rcode::NotAuth);
+        header.set_flag(Flags::QR, false);
+        header.write(&mut data).expect("error in writing header");
+        assert_eq!(data[2..4], [0x01, 0x89]);
+
+        let header = Header::parse(&data).expect("error in parsing header");
+        assert_eq!(header.rcode(), Rcode::NotAuth);
+        assert!(!header.qr());
+        assert!(header.write(&mut [0; 4]).is_err());
+    }
+
     #[test]
     fn parse_questions() {
         let packet = DnsPacket::parse(&[
@@ -155,13 +356,13 @@ mod test {
 
         assert_eq!(header.id, 0);
         assert!(!header.qr());
-        assert_eq!(header.opcode(), 0);
+        assert_eq!(header.opcode(), Opcode::Query);
         assert!(!header.aa());
         assert!(!header.tc());
         assert!(!header.rd());
         assert!(!header.ra());
         assert_eq!(header.z(), 0);
-        assert_eq!(header.rcode(), 0);
+        assert_eq!(header.rcode(), Rcode::NoError);
 
         assert_eq!(header.qd_count, 3);
         assert_eq!(header.an_count, 1);
@@ -171,6 +372,282 @@ mod test {
         assert_eq!(packet.questions.len(), 3);
     }
 
+    #[test]
+    fn reject_pointer_loops() {
+        // A question whose name points at itself
+        assert!(matches!(
+            DnsPacket::parse(&[
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x0c,
+            ]),
+            Err(ParserError::InvalidPointer(12))
+        ));
+
+        // Two names that point at each other
+        assert!(matches!(
+            DnsPacket::parse(&[
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x61,
+                0xc0, 0x16, 0x00, 0x01, 0x00, 0x01, 0x01, 0x62, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01,
+            ]),
+            Err(ParserError::InvalidPointer(_))
+        ));
+    }
+
+    #[test]
+    fn reject_short_data() {
+        // A question cut off after its name
+        assert!(matches!(
+            DnsPacket::parse(&[
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x70,
+                0x00,
+            ]),
+            Err(ParserError::UnexpectedEOP)
+        ));
+
+        for rtype in [Type::A, Type::Aaaa, Type::Srv] {
+            assert!(matches!(
+                RData::parse(rtype, &[], &[]),
+                Err(ParserError::UnexpectedEOP)
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_txt_attributes() {
+        let packet = DnsPacket::parse(&[
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x70,
+            0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00, 0x10, 0x80, 0x01, 0x00, 0x00, 0x11,
+            0x94, 0x00, 0x30, 0x09, 0x74, 0x78, 0x74, 0x76, 0x65, 0x72, 0x73, 0x3d, 0x31, 0x19,
+            0x70, 0x64, 0x6c, 0x3d, 0x61, 0x70, 0x70, 0x6c, 0x69, 0x63, 0x61, 0x74, 0x69, 0x6f,
+            0x6e, 0x2f, 0x70, 0x64, 0x66, 0x2c, 0x69, 0x6d, 0x61, 0x67, 0x65, 0x05, 0x43, 0x6f,
+            0x6c, 0x6f, 0x72, 0x05, 0x6e, 0x6f, 0x74, 0x65, 0x3d,
+        ])
+        .expect("error in parsing input");
+
+        let RData::Txt(ref txt) = packet.answers[0].rdata else {
+            panic!("expected a TXT record");
+        };
+
+        assert_eq!(txt.strings.len(), 4);
+        assert_eq!(txt.attributes().count(), 4);
+        assert_eq!(
+            txt.get("PDL")
+                .and_then(|a| a.value_str().map(str::to_owned)),
+            Some("application/pdf,image".to_string())
+        );
+        assert_eq!(
+            txt.get("color"),
+            Some(TxtAttribute {
+                key: "Color",
+                value: None
+            })
+        );
+        assert_eq!(txt.get("note").and_then(|a| a.value), Some(&[][..]));
+        assert!(txt.get("ty").is_none());
+    }
+
+    #[test]
+    fn parse_opt_owner() {
+        let data = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x70,
+            0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x29,
+            0x05, 0xa0, 0x00, 0x00, 0x80, 0x00, 0x00, 0x12, 0x00, 0x04, 0x00, 0x0e, 0x00, 0x2a,
+            0x4e, 0x1f, 0x23, 0x7d, 0x4f, 0x03, 0x4e, 0x1f, 0x23, 0x7d, 0x4f, 0x04,
+        ];
+
+        let packet = DnsPacket::parse(&data).expect("error in parsing input");
+        let RData::Opt(ref opt) = packet.additional[0].rdata else {
+            panic!("expected an OPT record");
+        };
+
+        assert!(!packet.additional[0].cache_flush);
+        assert_eq!(opt.udp_payload_size, 1440);
+        assert_eq!(opt.extended_rcode, 0);
+        assert_eq!(opt.version, 0);
+        assert!(opt.dnssec_ok);
+
+        let owner = opt.owner().expect("missing owner option");
+        assert_eq!(owner.sequence, 0x2a);
+        assert_eq!(owner.primary_mac, [0x4e, 0x1f, 0x23, 0x7d, 0x4f, 0x03]);
+        assert_eq!(owner.wakeup_mac, Some([0x4e, 0x1f, 0x23, 0x7d, 0x4f, 0x04]));
+        assert!(owner.password.is_none());
+
+        let stripped = DnsPacket::strip_edns_options(&data)
+            .expect("error in stripping options")
+            .expect("missing OPT record");
+        let packet = DnsPacket::parse(&stripped).expect("error in parsing stripped packet");
+        let RData::Opt(ref opt) = packet.additional[0].rdata else {
+            panic!("expected an OPT record");
+        };
+
+        assert_eq!(stripped.len(), data.len() - 18);
+        assert!(opt.options.is_empty());
+        assert_eq!(opt.udp_payload_size, 1440);
+    }
+
+    #[test]
+    fn write_goodbye() {
+        let data = [
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x04, 0x5f,
+            0x69, 0x70, 0x70, 0x04, 0x5f, 0x74, 0x63, 0x70, 0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c,
+            0x00, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x0a, 0x07, 0x50, 0x72,
+            0x69, 0x6e, 0x74, 0x65, 0x72, 0xc0, 0x0c, 0xc0, 0x27, 0x00, 0x21, 0x80, 0x01, 0x00,
+            0x00, 0x00, 0x78, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x02, 0x77, 0x07, 0x70, 0x72,
+            0x69, 0x6e, 0x74, 0x65, 0x72, 0xc0, 0x16, 0xc0, 0x43, 0x00, 0x01, 0x80, 0x01, 0x00,
+            0x00, 0x00, 0x78, 0x00, 0x04, 0xc0, 0x00, 0x02, 0x05,
+        ];
+
+        let goodbye = DnsPacket::goodbye(&data)
+            .expect("error in writing goodbye")
+            .expect("missing PTR answer");
+        let packet = DnsPacket::parse(&goodbye).expect("error in parsing goodbye");
+
+        // The SRV and A records are unique to the host and stay cached
+        assert_eq!(packet.header.an_count, 1);
+        assert_eq!(packet.answers[0].name, "_ipp._tcp.local");
+        assert_eq!(packet.answers[0].ttl, 0);
+        assert!(!packet.answers[0].cache_flush);
+        assert_eq!(
+            packet.answers[0].rdata.ptr_target(),
+            Some("Printer._ipp._tcp.local")
+        );
+
+        // Only host addresses, nothing to say goodbye to
+        let data = [
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x70,
+            0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00, 0x01, 0x80, 0x01, 0x00, 0x00, 0x00,
+            0x78, 0x00, 0x04, 0xc0, 0x00, 0x02, 0x05,
+        ];
+        assert!(DnsPacket::goodbye(&data)
+            .expect("error in writing goodbye")
+            .is_none());
+    }
+
+    #[test]
+    fn parse_https_svc_params() {
+        let data = [
+            0x56, 0x48, 0x81, 0xa0, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0a, 0x63,
+            0x6c, 0x6f, 0x75, 0x64, 0x66, 0x6c, 0x61, 0x72, 0x65, 0x03, 0x63, 0x6f, 0x6d, 0x00,
+            0x00, 0x41, 0x00, 0x01, 0xc0, 0x0c, 0x00, 0x41, 0x00, 0x01, 0x00, 0x00, 0x06, 0xd8,
+            0x00, 0x3d, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x06, 0x02, 0x68, 0x33, 0x02, 0x68,
+            0x32, 0x00, 0x04, 0x00, 0x08, 0x68, 0x10, 0x84, 0xe5, 0x68, 0x10, 0x85, 0xe5, 0x00,
+            0x06, 0x00, 0x20, 0x26, 0x06, 0x47, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x68, 0x10, 0x84, 0xe5, 0x26, 0x06, 0x47, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x68, 0x10, 0x85, 0xe5, 0x00, 0x00, 0x29, 0x04, 0xd0, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let packet = DnsPacket::parse(&data).expect("error in parsing input");
+        let RData::Https(ref https) = packet.answers[0].rdata else {
+            panic!("expected an HTTPS record");
+        };
+
+        assert_eq!(https.svc_priority, 1);
+        assert_eq!(
+            https.svc_params[0],
+            SvcParam::Alpn(vec!["h3".to_string(), "h2".to_string()])
+        );
+        assert_eq!(
+            packet.answers[0].rdata.addresses(),
+            vec![
+                "104.16.132.229".parse::<std::net::IpAddr>().unwrap(),
+                "104.16.133.229".parse().unwrap(),
+                "2606:4700::6810:84e5".parse().unwrap(),
+                "2606:4700::6810:85e5".parse().unwrap(),
+            ]
+        );
+        assert_eq!(
+            https.to_string(),
+            "1 . alpn=h3,h2 ipv4hint=104.16.132.229,104.16.133.229 \
+             ipv6hint=2606:4700::6810:84e5,2606:4700::6810:85e5"
+        );
+
+        // alpn claims 6 bytes but only 2 are present
+        let truncated = [0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x06, 0x02, 0x68];
+        assert!(RData::parse(Type::Https, &truncated, &truncated).is_err());
+        assert!(RData::parse(Type::Svcb, &truncated[..6], &truncated).is_err());
+    }
+
+    #[test]
+    fn display_packet() {
+        let packet = DnsPacket::parse(&[
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x07, 0x70,
+            0x72, 0x69, 0x6e, 0x74, 0x65, 0x72, 0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00,
+            0x01, 0x80, 0x01, 0xc0, 0x0c, 0x00, 0x01, 0x80, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00,
+            0x04, 0x0a, 0x00, 0x00, 0x05, 0xc0, 0x0c, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00, 0x11,
+            0x94, 0x00, 0x0e, 0x09, 0x74, 0x78, 0x74, 0x76, 0x65, 0x72, 0x73, 0x3d, 0x31, 0x03,
+            0x61, 0x22, 0x62,
+        ])
+        .expect("error in parsing input");
+
+        assert_eq!(
+            packet.to_string(),
+            ";; opcode: QUERY, status: NOERROR, id: 0
+;; flags: qr aa; QUERY: 1, ANSWER: 2, AUTHORITY: 0, ADDITIONAL: 0
+
+;; QUESTION SECTION:
+;printer.local. IN A QU
+
+;; ANSWER SECTION:
+printer.local. 120 IN A 10.0.0.5
+printer.local. 4500 IN TXT \"txtvers=1\" \"a\\\"b\""
+        );
+        assert_eq!(
+            packet.summary().to_string(),
+            "response id=0 qd=[printer.local. A] an=[printer.local. A, printer.local. TXT]"
+        );
+    }
+
+    #[test]
+    fn parse_name_records() {
+        let packet = DnsPacket::parse(&[
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, 0x07, 0x70,
+            0x72, 0x69, 0x6e, 0x74, 0x65, 0x72, 0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00,
+            0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x0c, 0x04, 0x63, 0x75, 0x70, 0x73,
+            0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00, 0xc0, 0x0c, 0x00, 0x02, 0x00, 0x01, 0x00,
+            0x00, 0x00, 0x78, 0x00, 0x05, 0x02, 0x6e, 0x73, 0xc0, 0x0c, 0xc0, 0x0c, 0x00, 0x06,
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x23, 0xc0, 0x0c, 0x05, 0x61, 0x64, 0x6d,
+            0x69, 0x6e, 0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00,
+            0x00, 0x0e, 0x10, 0x00, 0x00, 0x02, 0x58, 0x00, 0x01, 0x51, 0x80, 0x00, 0x00, 0x00,
+            0x78, 0xc0, 0x0c, 0x00, 0x0d, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x0c, 0x05,
+            0x41, 0x52, 0x4d, 0x36, 0x34, 0x05, 0x4c, 0x69, 0x6e, 0x75, 0x78, 0xc0, 0x0c, 0x00,
+            0x0f, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x09, 0x00, 0x0a, 0x04, 0x6d, 0x61,
+            0x69, 0x6c, 0xc0, 0x0c, 0xc0, 0x0c, 0x00, 0x27, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78,
+            0x00, 0x09, 0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x00,
+        ])
+        .expect("error in parsing input");
+
+        let records: Vec<_> = packet.answers.iter().map(|rr| rr.to_string()).collect();
+        assert_eq!(
+            records,
+            [
+                "printer.local. 120 IN CNAME cups.local.",
+                "printer.local. 120 IN NS ns.printer.local.",
+                "printer.local. 120 IN SOA printer.local. admin.local. 1 3600 600 86400 120",
+                "printer.local. 120 IN HINFO \"ARM64\" \"Linux\"",
+                "printer.local. 120 IN MX 10 mail.printer.local.",
+                "printer.local. 120 IN DNAME example.",
+            ]
+        );
+        assert_eq!(packet.answers[0].rdata.alias_target(), Some("cups.local"));
+
+        let RData::Dname(ref dname) = packet.answers[5].rdata else {
+            panic!("expected a DNAME record");
+        };
+        assert_eq!(packet.answers[5].rdata.alias_target(), None);
+        assert_eq!(
+            dname.substitute("printer.local", "ipp.Printer.local"),
+            Some("ipp.example".to_string())
+        );
+        assert_eq!(dname.substitute("printer.local", "printer.local"), None);
+        assert_eq!(
+            dname.substitute("printer.local", "laserprinter.local"),
+            None
+        );
+
+        // CNAME with an empty rdata
+        assert!(RData::parse(Type::Cname, &[], &[]).is_err());
+    }
+
     #[test]
     fn parse_samples() {
         let inputs = [
@@ -1,5 +1,8 @@
 use crate::ParserError;
 
+/// Compression pointers followed per name before giving up
+const MAX_POINTERS: usize = 128;
+
 #[derive(Debug, Default)]
 pub struct Qname {}
 
@@ -8,11 +11,19 @@ impl Qname {
         let mut parse_data = data;
         let mut out = Vec::new();
         let mut offset = 0;
-        let mut byte = parse_data[offset];
+        let mut byte = *parse_data.first().ok_or(ParserError::UnexpectedEOP)?;
         let mut bytes_read = 1;
 
         let mut return_pos: Option<usize> = None;
 
+        // Position of the current run of labels in `original`. Pointers have to point before it,
+        // so every jump goes further back and a loop can't form.
+        let mut start = (data.as_ptr() as usize)
+            .checked_sub(original.as_ptr() as usize)
+            .filter(|&start| start <= original.len())
+            .unwrap_or(original.len());
+        let mut pointers = 0;
+
         loop {
             match byte {
                 0 => {
@@ -29,22 +40,36 @@ impl Qname {
                 }
 
                 v if v & 0b1100_0000 == 0b1100_0000 => {
+                    if offset + 1 >= parse_data.len() {
+                        return Err(ParserError::UnexpectedEOP);
+                    }
+
                     let nof = (((v & 0b0011_1111) as usize) << 8) | parse_data[offset + 1] as usize;
 
                     if nof >= original.len() {
                         return Err(ParserError::UnexpectedEOP);
                     }
 
+                    pointers += 1;
+                    if nof >= start || pointers > MAX_POINTERS {
+                        return Err(ParserError::InvalidPointer(nof));
+                    }
+
                     if return_pos.is_none() {
                         return_pos = Some(offset);
                     }
 
                     offset = 0;
+                    start = nof;
                     parse_data = &original[nof..];
                     byte = parse_data[0];
                 }
 
                 _ => {
+                    if offset + 1 + byte as usize >= parse_data.len() {
+                        return Err(ParserError::UnexpectedEOP);
+                    }
+
                     out.extend(&parse_data[offset + 1..offset + 1 + byte as usize]);
                     out.push(b'.');
 
@@ -56,4 +81,28 @@ impl Qname {
             }
         }
     }
+
+    /// Appends the name at the start of `data` to `out` in wire format with every compression
+    /// pointer resolved, so it can be moved to another packet
+    pub fn copy(data: &[u8], original: &[u8], out: &mut Vec<u8>) -> Result<(), ParserError> {
+        // Reading checks the bounds and pointers, so the walk below can't fail or loop
+        Qname::read(data, original)?;
+
+        let mut data = data;
+        loop {
+            let len = data[0];
+            if len & 0b1100_0000 == 0b1100_0000 {
+                let nof = (((len & 0b0011_1111) as usize) << 8) | data[1] as usize;
+                data = &original[nof..];
+                continue;
+            }
+
+            out.extend_from_slice(&data[..=len as usize]);
+            if len == 0 {
+                return Ok(());
+            }
+
+            data = &data[len as usize + 1..];
+        }
+    }
 }
@@ -1,4 +1,5 @@
-use crate::{ParserError, Qname};
+use crate::{class_name, type_name, ParserError, Qname};
+use std::fmt;
 
 #[derive(Debug, Default)]
 pub struct Question {
@@ -11,6 +12,9 @@ pub struct Question {
 impl Question {
     pub fn parse(data: &[u8], original: &[u8]) -> Result<(Self, usize), ParserError> {
         let (qname, mut read) = Qname::read(data, original)?;
+        if read + 4 > data.len() {
+            return Err(ParserError::UnexpectedEOP);
+        }
 
         let qtype = u16::from_be_bytes([data[read], data[read + 1]]);
         read += 2;
@@ -31,3 +35,23 @@ impl Question {
         ))
     }
 }
+
+/// dig style question line, e.g. `;printer.local. IN A`. Questions with the mDNS unicast
+/// response bit set are suffixed with `QU`.
+impl fmt::Display for Question {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            ";{}. {} {}",
+            self.qname,
+            class_name(self.qclass),
+            type_name(self.qtype)
+        )?;
+
+        if self.unicast_preferred {
+            write!(f, " QU")?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use crate::ParserError;
+use std::fmt;
 use std::net::Ipv4Addr;
 
 #[derive(Debug)]
@@ -8,8 +9,18 @@ pub struct Record {
 
 impl Record {
     pub fn parse(data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
+        if data.len() < 4 {
+            return Err(ParserError::UnexpectedEOP);
+        }
+
         let address = Ipv4Addr::from([data[0], data[1], data[2], data[3]]);
 
         Ok(Self { address })
     }
 }
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address)
+    }
+}
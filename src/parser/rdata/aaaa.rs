@@ -1,4 +1,5 @@
 use crate::ParserError;
+use std::fmt;
 use std::net::Ipv6Addr;
 
 #[derive(Debug)]
@@ -8,6 +9,10 @@ pub struct Record {
 
 impl Record {
     pub fn parse(mut data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
+        if data.len() < 16 {
+            return Err(ParserError::UnexpectedEOP);
+        }
+
         let address = Ipv6Addr::from([
             data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7], data[8],
             data[9], data[10], data[11], data[12], data[13], data[14], data[15],
@@ -16,3 +21,9 @@ impl Record {
         Ok(Self { address })
     }
 }
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address)
+    }
+}
@@ -1,10 +1,21 @@
-use crate::ParserError;
+use crate::{ParserError, Qname};
+use std::fmt;
 
 #[derive(Debug)]
-pub struct Record {}
+pub struct Record {
+    pub target: String,
+}
 
 impl Record {
     pub fn parse(data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
-        Ok(Self {})
+        let (target, _) = Qname::read(data, original)?;
+
+        Ok(Self { target })
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.", self.target)
     }
 }
@@ -0,0 +1,40 @@
+use crate::{ParserError, Qname};
+use std::fmt;
+
+#[derive(Debug)]
+pub struct Record {
+    pub target: String,
+}
+
+impl Record {
+    pub fn parse(data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
+        let (target, _) = Qname::read(data, original)?;
+
+        Ok(Self { target })
+    }
+
+    /// Rewrites `name` if it is below `owner`, the owner name of this record, by replacing the
+    /// `owner` suffix with the target (RFC 6672). The owner name itself isn't redirected.
+    pub fn substitute(&self, owner: &str, name: &str) -> Option<String> {
+        let split = name.len().checked_sub(owner.len() + 1)?;
+        if !name.is_char_boundary(split)
+            || name.as_bytes()[split] != b'.'
+            || !name[split + 1..].eq_ignore_ascii_case(owner)
+        {
+            return None;
+        }
+
+        let prefix = &name[..split];
+        Some(if self.target.is_empty() {
+            prefix.to_string()
+        } else {
+            format!("{}.{}", prefix, self.target)
+        })
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.", self.target)
+    }
+}
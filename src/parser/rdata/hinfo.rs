@@ -0,0 +1,38 @@
+use super::txt::write_character_string;
+use crate::ParserError;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct Record {
+    pub cpu: Vec<u8>,
+    pub os: Vec<u8>,
+}
+
+impl Record {
+    pub fn parse(data: &[u8], _original: &[u8]) -> Result<Self, ParserError> {
+        let (cpu, data) = read_character_string(data)?;
+        let (os, _) = read_character_string(data)?;
+
+        Ok(Self {
+            cpu: cpu.to_vec(),
+            os: os.to_vec(),
+        })
+    }
+}
+
+fn read_character_string(data: &[u8]) -> Result<(&[u8], &[u8]), ParserError> {
+    let length = *data.first().ok_or(ParserError::UnexpectedEOP)? as usize;
+    if 1 + length > data.len() {
+        return Err(ParserError::UnexpectedEOP);
+    }
+
+    Ok((&data[1..1 + length], &data[1 + length..]))
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_character_string(f, &self.cpu)?;
+        write!(f, " ")?;
+        write_character_string(f, &self.os)
+    }
+}
@@ -1,22 +1,41 @@
 mod a;
 mod aaaa;
 mod cname;
-mod https;
+mod dname;
+mod hinfo;
+mod mx;
+mod ns;
 mod nsec;
+mod opt;
 mod ptr;
+mod soa;
 mod srv;
+mod svcb;
 mod txt;
 use crate::{ParserError, Type};
+use std::fmt;
+use std::net::IpAddr;
+
+pub use opt::{EdnsOption, Owner as EdnsOwner};
+pub use svcb::SvcParam;
+pub use txt::Attribute as TxtAttribute;
 
 #[derive(Debug)]
 pub enum RData {
     A(a::Record),
     Aaaa(aaaa::Record),
+    Ns(ns::Record),
     Cname(cname::Record),
+    Soa(soa::Record),
+    Hinfo(hinfo::Record),
+    Mx(mx::Record),
+    Dname(dname::Record),
     Ptr(ptr::Record),
     Txt(txt::Record),
     Srv(srv::Record),
-    Https(https::Record),
+    Svcb(svcb::Record),
+    Https(svcb::Record),
+    Opt(opt::Record),
 
     Unknown(Type, Vec<u8>),
 }
@@ -26,14 +45,85 @@ impl RData {
         use RData::*;
         match rtype {
             Type::A => Ok(A(a::Record::parse(data, original)?)),
+            Type::Ns => Ok(Ns(ns::Record::parse(data, original)?)),
             Type::Cname => Ok(Cname(cname::Record::parse(data, original)?)),
+            Type::Soa => Ok(Soa(soa::Record::parse(data, original)?)),
+            Type::Hinfo => Ok(Hinfo(hinfo::Record::parse(data, original)?)),
+            Type::Mx => Ok(Mx(mx::Record::parse(data, original)?)),
+            Type::Dname => Ok(Dname(dname::Record::parse(data, original)?)),
             Type::Ptr => Ok(Ptr(ptr::Record::parse(data, original)?)),
             Type::Txt => Ok(Txt(txt::Record::parse(data, original)?)),
             Type::Aaaa => Ok(Aaaa(aaaa::Record::parse(data, original)?)),
             Type::Srv => Ok(Srv(srv::Record::parse(data, original)?)),
-            Type::Https => Ok(Https(https::Record::parse(data, original)?)),
+            Type::Svcb => Ok(Svcb(svcb::Record::parse(data, original)?)),
+            Type::Https => Ok(Https(svcb::Record::parse(data, original)?)),
 
             _ => Ok(Unknown(rtype, data.to_vec())),
         }
     }
+
+    /// Addresses carried by the record, including SVCB/HTTPS ipv4hint and ipv6hint values
+    pub fn addresses(&self) -> Vec<IpAddr> {
+        match self {
+            RData::A(r) => vec![IpAddr::V4(r.address)],
+            RData::Aaaa(r) => vec![IpAddr::V6(r.address)],
+            RData::Svcb(r) | RData::Https(r) => r.hint_addresses().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Domain name a CNAME record points its owner name to. DNAME records redirect the names
+    /// below their owner instead, see `dname::Record::substitute`.
+    pub fn alias_target(&self) -> Option<&str> {
+        match self {
+            RData::Cname(r) => Some(&r.target),
+            _ => None,
+        }
+    }
+
+    /// Name a PTR record points to, e.g. the service instance of a DNS-SD browse answer
+    pub fn ptr_target(&self) -> Option<&str> {
+        match self {
+            RData::Ptr(r) => Some(r.domain_name()),
+            _ => None,
+        }
+    }
+
+    /// OPT records reuse the CLASS and TTL fields so they need both to be decoded.
+    pub fn parse_opt(class: u16, ttl: u32, data: &[u8]) -> Result<Self, ParserError> {
+        Ok(RData::Opt(opt::Record::parse(class, ttl, data)?))
+    }
+}
+
+impl fmt::Display for RData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RData::A(r) => write!(f, "{}", r),
+            RData::Aaaa(r) => write!(f, "{}", r),
+            RData::Ns(r) => write!(f, "{}", r),
+            RData::Cname(r) => write!(f, "{}", r),
+            RData::Soa(r) => write!(f, "{}", r),
+            RData::Hinfo(r) => write!(f, "{}", r),
+            RData::Mx(r) => write!(f, "{}", r),
+            RData::Dname(r) => write!(f, "{}", r),
+            RData::Ptr(r) => write!(f, "{}", r),
+            RData::Txt(r) => write!(f, "{}", r),
+            RData::Srv(r) => write!(f, "{}", r),
+            RData::Svcb(r) | RData::Https(r) => write!(f, "{}", r),
+            RData::Opt(r) => write!(f, "{}", r),
+
+            // RFC 3597 Section 5
+            RData::Unknown(_, data) => {
+                write!(f, "\\# {}", data.len())?;
+                if !data.is_empty() {
+                    write!(f, " ")?;
+                }
+                for b in data {
+                    write!(f, "{:02x}", b)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
 }
@@ -0,0 +1,30 @@
+use crate::{ParserError, Qname};
+use std::fmt;
+
+#[derive(Debug)]
+pub struct Record {
+    pub preference: u16,
+    pub exchange: String,
+}
+
+impl Record {
+    pub fn parse(data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
+        if data.len() < 3 {
+            return Err(ParserError::UnexpectedEOP);
+        }
+
+        let preference = u16::from_be_bytes([data[0], data[1]]);
+        let (exchange, _) = Qname::read(&data[2..], original)?;
+
+        Ok(Self {
+            preference,
+            exchange,
+        })
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}.", self.preference, self.exchange)
+    }
+}
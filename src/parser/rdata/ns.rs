@@ -0,0 +1,21 @@
+use crate::{ParserError, Qname};
+use std::fmt;
+
+#[derive(Debug)]
+pub struct Record {
+    pub name_server: String,
+}
+
+impl Record {
+    pub fn parse(data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
+        let (name_server, _) = Qname::read(data, original)?;
+
+        Ok(Self { name_server })
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.", self.name_server)
+    }
+}
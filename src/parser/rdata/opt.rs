@@ -0,0 +1,166 @@
+use crate::ParserError;
+use std::fmt;
+
+/// EDNS0 pseudo record. RFC 6891 Section 6.1
+///
+/// The CLASS and TTL fields of an OPT record are repurposed, so they are decoded here instead
+/// of being treated as `class`/`cache_flush`/`ttl` like the other records.
+#[derive(Debug)]
+pub struct Record {
+    pub udp_payload_size: u16,
+    pub extended_rcode: u8,
+    pub version: u8,
+    pub dnssec_ok: bool,
+    pub options: Vec<EdnsOption>,
+}
+
+#[derive(Debug)]
+pub enum EdnsOption {
+    Owner(Owner),
+    Unknown { code: u16, data: Vec<u8> },
+}
+
+/// EDNS0 Owner option, sent by Apple devices to a Sleep Proxy.
+/// https://datatracker.ietf.org/doc/html/draft-cheshire-edns0-owner-option
+#[derive(Debug)]
+pub struct Owner {
+    pub version: u8,
+    pub sequence: u8,
+    pub primary_mac: [u8; 6],
+    pub wakeup_mac: Option<[u8; 6]>,
+    pub password: Option<Vec<u8>>,
+}
+
+impl Record {
+    pub fn parse(class: u16, ttl: u32, mut data: &[u8]) -> Result<Self, ParserError> {
+        let mut options = vec![];
+
+        while !data.is_empty() {
+            if data.len() < 4 {
+                return Err(ParserError::UnexpectedEOP);
+            }
+
+            let code = u16::from_be_bytes([data[0], data[1]]);
+            let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+            if 4 + length > data.len() {
+                return Err(ParserError::UnexpectedEOP);
+            }
+
+            options.push(EdnsOption::parse(code, &data[4..4 + length]));
+            data = &data[4 + length..];
+        }
+
+        Ok(Self {
+            udp_payload_size: class,
+            extended_rcode: (ttl >> 24) as u8,
+            version: (ttl >> 16) as u8,
+            dnssec_ok: ((ttl >> 15) & 1) == 1,
+            options,
+        })
+    }
+
+    pub fn owner(&self) -> Option<&Owner> {
+        self.options.iter().find_map(|o| match o {
+            EdnsOption::Owner(owner) => Some(owner),
+            _ => None,
+        })
+    }
+}
+
+impl EdnsOption {
+    pub const OWNER: u16 = 4;
+
+    fn parse(code: u16, data: &[u8]) -> Self {
+        match code {
+            Self::OWNER => match Owner::parse(data) {
+                Some(owner) => EdnsOption::Owner(owner),
+                None => EdnsOption::Unknown {
+                    code,
+                    data: data.to_vec(),
+                },
+            },
+
+            _ => EdnsOption::Unknown {
+                code,
+                data: data.to_vec(),
+            },
+        }
+    }
+
+    pub fn code(&self) -> u16 {
+        match self {
+            EdnsOption::Owner(_) => Self::OWNER,
+            EdnsOption::Unknown { code, .. } => *code,
+        }
+    }
+}
+
+impl Owner {
+    fn parse(data: &[u8]) -> Option<Self> {
+        // Valid lengths are 8 (primary MAC), 14 (+ wakeup MAC) and 18 or 20 (+ password)
+        if !matches!(data.len(), 8 | 14 | 18 | 20) {
+            return None;
+        }
+
+        let mac = |d: &[u8]| -> [u8; 6] { [d[0], d[1], d[2], d[3], d[4], d[5]] };
+
+        Some(Self {
+            version: data[0],
+            sequence: data[1],
+            primary_mac: mac(&data[2..8]),
+            wakeup_mac: (data.len() >= 14).then(|| mac(&data[8..14])),
+            password: (data.len() > 14).then(|| data[14..].to_vec()),
+        })
+    }
+}
+
+fn write_mac(f: &mut fmt::Formatter<'_>, mac: &[u8; 6]) -> fmt::Result {
+    write!(
+        f,
+        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    )
+}
+
+impl fmt::Display for EdnsOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdnsOption::Owner(owner) => {
+                write!(f, "OWNER: v{} seq {} ", owner.version, owner.sequence)?;
+                write_mac(f, &owner.primary_mac)?;
+
+                if let Some(ref mac) = owner.wakeup_mac {
+                    write!(f, " wakeup ")?;
+                    write_mac(f, mac)?;
+                }
+
+                Ok(())
+            }
+            EdnsOption::Unknown { code, data } => {
+                write!(f, "OPT={}: ", code)?;
+                for b in data {
+                    write!(f, "{:02x}", b)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Mirrors the OPT pseudosection printed by dig, e.g. `; EDNS: version: 0, flags: do; udp: 1440`
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "; EDNS: version: {}, flags:", self.version)?;
+        if self.dnssec_ok {
+            write!(f, " do")?;
+        }
+        write!(f, "; udp: {}", self.udp_payload_size)?;
+
+        for option in &self.options {
+            write!(f, "; {}", option)?;
+        }
+
+        Ok(())
+    }
+}
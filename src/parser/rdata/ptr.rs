@@ -1,4 +1,5 @@
 use crate::{parser::qname, ParserError, Qname};
+use std::fmt;
 
 #[derive(Debug)]
 pub struct Record {
@@ -11,4 +12,14 @@ impl Record {
 
         Ok(Self { domain_name })
     }
+
+    pub fn domain_name(&self) -> &str {
+        &self.domain_name
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.", self.domain_name)
+    }
 }
@@ -0,0 +1,58 @@
+use crate::{ParserError, Qname};
+use std::fmt;
+
+#[derive(Debug)]
+pub struct Record {
+    pub mname: String,
+    pub rname: String,
+    pub serial: u32,
+    pub refresh: u32,
+    pub retry: u32,
+    pub expire: u32,
+    pub minimum: u32,
+}
+
+impl Record {
+    pub fn parse(mut data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
+        let (mname, read) = Qname::read(data, original)?;
+        data = &data[read..];
+        if data.is_empty() {
+            return Err(ParserError::UnexpectedEOP);
+        }
+
+        let (rname, read) = Qname::read(data, original)?;
+        data = &data[read..];
+        if data.len() < 20 {
+            return Err(ParserError::UnexpectedEOP);
+        }
+
+        let u32_at =
+            |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+
+        Ok(Self {
+            mname,
+            rname,
+            serial: u32_at(0),
+            refresh: u32_at(4),
+            retry: u32_at(8),
+            expire: u32_at(12),
+            minimum: u32_at(16),
+        })
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}. {}. {} {} {} {} {}",
+            self.mname,
+            self.rname,
+            self.serial,
+            self.refresh,
+            self.retry,
+            self.expire,
+            self.minimum
+        )
+    }
+}
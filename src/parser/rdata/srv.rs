@@ -1,4 +1,5 @@
 use crate::{ParserError, Qname};
+use std::fmt;
 
 #[derive(Debug)]
 pub struct Record {
@@ -10,6 +11,10 @@ pub struct Record {
 
 impl Record {
     pub fn parse(mut data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
+        if data.len() < 7 {
+            return Err(ParserError::UnexpectedEOP);
+        }
+
         let priority = u16::from_be_bytes([data[0], data[1]]);
         data = &data[2..];
         let weight = u16::from_be_bytes([data[0], data[1]]);
@@ -27,3 +32,13 @@ impl Record {
         })
     }
 }
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}.",
+            self.priority, self.weight, self.port, self.target
+        )
+    }
+}
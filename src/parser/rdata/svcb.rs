@@ -0,0 +1,256 @@
+use crate::{ParserError, Qname};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// SVCB and HTTPS records share the same wire format. RFC 9460 Section 2.2
+#[derive(Debug)]
+pub struct Record {
+    pub svc_priority: u16,
+    pub target_name: String,
+    pub svc_params: Vec<SvcParam>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SvcParam {
+    Mandatory(Vec<u16>),
+    Alpn(Vec<String>),
+    NoDefaultAlpn,
+    Port(u16),
+    Ipv4Hint(Vec<Ipv4Addr>),
+    Ech(Vec<u8>),
+    Ipv6Hint(Vec<Ipv6Addr>),
+    Unknown { key: u16, value: Vec<u8> },
+}
+
+impl Record {
+    pub fn parse(mut data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
+        if data.len() < 3 {
+            return Err(ParserError::UnexpectedEOP);
+        }
+
+        let svc_priority = u16::from_be_bytes([data[0], data[1]]);
+        data = &data[2..];
+
+        let (target_name, read) = Qname::read(data, original)?;
+        data = &data[read..];
+
+        let mut svc_params = vec![];
+
+        while !data.is_empty() {
+            if data.len() < 4 {
+                return Err(ParserError::UnexpectedEOP);
+            }
+
+            let key = u16::from_be_bytes([data[0], data[1]]);
+            let value_length = u16::from_be_bytes([data[2], data[3]]) as usize;
+            if 4 + value_length > data.len() {
+                return Err(ParserError::UnexpectedEOP);
+            }
+
+            svc_params.push(SvcParam::parse(key, &data[4..4 + value_length])?);
+
+            data = &data[4 + value_length..];
+        }
+
+        Ok(Self {
+            svc_priority,
+            target_name,
+            svc_params,
+        })
+    }
+
+    /// Every address in the ipv4hint and ipv6hint parameters
+    pub fn hint_addresses(&self) -> impl Iterator<Item = IpAddr> + '_ {
+        self.svc_params.iter().flat_map(|p| -> Vec<IpAddr> {
+            match p {
+                SvcParam::Ipv4Hint(v) => v.iter().map(|&a| IpAddr::V4(a)).collect(),
+                SvcParam::Ipv6Hint(v) => v.iter().map(|&a| IpAddr::V6(a)).collect(),
+                _ => vec![],
+            }
+        })
+    }
+}
+
+impl SvcParam {
+    fn parse(key: u16, value: &[u8]) -> Result<Self, ParserError> {
+        let invalid = ParserError::InvalidSvcParam(key);
+
+        Ok(match key {
+            0 => {
+                if !value.len().is_multiple_of(2) {
+                    return Err(invalid);
+                }
+
+                SvcParam::Mandatory(
+                    value
+                        .chunks_exact(2)
+                        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                        .collect(),
+                )
+            }
+
+            1 => {
+                let mut ids = vec![];
+                let mut value = value;
+
+                while !value.is_empty() {
+                    let length = value[0] as usize;
+                    if length == 0 || 1 + length > value.len() {
+                        return Err(invalid);
+                    }
+
+                    ids.push(String::from_utf8_lossy(&value[1..1 + length]).into_owned());
+                    value = &value[1 + length..];
+                }
+
+                SvcParam::Alpn(ids)
+            }
+
+            2 if value.is_empty() => SvcParam::NoDefaultAlpn,
+
+            3 if value.len() == 2 => SvcParam::Port(u16::from_be_bytes([value[0], value[1]])),
+
+            4 if !value.is_empty() && value.len().is_multiple_of(4) => SvcParam::Ipv4Hint(
+                value
+                    .chunks_exact(4)
+                    .map(|c| Ipv4Addr::new(c[0], c[1], c[2], c[3]))
+                    .collect(),
+            ),
+
+            5 => SvcParam::Ech(value.to_vec()),
+
+            6 if !value.is_empty() && value.len().is_multiple_of(16) => SvcParam::Ipv6Hint(
+                value
+                    .chunks_exact(16)
+                    .map(|c| Ipv6Addr::from(<[u8; 16]>::try_from(c).unwrap()))
+                    .collect(),
+            ),
+
+            2..=6 => return Err(invalid),
+
+            _ => SvcParam::Unknown {
+                key,
+                value: value.to_vec(),
+            },
+        })
+    }
+
+    pub fn key(&self) -> u16 {
+        match self {
+            SvcParam::Mandatory(_) => 0,
+            SvcParam::Alpn(_) => 1,
+            SvcParam::NoDefaultAlpn => 2,
+            SvcParam::Port(_) => 3,
+            SvcParam::Ipv4Hint(_) => 4,
+            SvcParam::Ech(_) => 5,
+            SvcParam::Ipv6Hint(_) => 6,
+            SvcParam::Unknown { key, .. } => *key,
+        }
+    }
+}
+
+fn key_name(key: u16) -> String {
+    match key {
+        0 => "mandatory".to_string(),
+        1 => "alpn".to_string(),
+        2 => "no-default-alpn".to_string(),
+        3 => "port".to_string(),
+        4 => "ipv4hint".to_string(),
+        5 => "ech".to_string(),
+        6 => "ipv6hint".to_string(),
+        key => format!("key{}", key),
+    }
+}
+
+fn join<T: fmt::Display>(f: &mut fmt::Formatter<'_>, values: &[T]) -> fmt::Result {
+    for (i, v) in values.iter().enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}", v)?;
+    }
+
+    Ok(())
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+/// Presentation format as described in RFC 9460 Section 2.1, e.g. `alpn=h3,h2`
+impl fmt::Display for SvcParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", key_name(self.key()))?;
+
+        match self {
+            SvcParam::Mandatory(keys) => {
+                write!(f, "=")?;
+                join(f, &keys.iter().map(|&k| key_name(k)).collect::<Vec<_>>())
+            }
+            SvcParam::Alpn(ids) => {
+                let ids: Vec<_> = ids
+                    .iter()
+                    .map(|id| id.replace('\\', "\\\\").replace(',', "\\,"))
+                    .collect();
+
+                write!(f, "=")?;
+                join(f, &ids)
+            }
+            SvcParam::NoDefaultAlpn => Ok(()),
+            SvcParam::Port(port) => write!(f, "={}", port),
+            SvcParam::Ipv4Hint(v) => {
+                write!(f, "=")?;
+                join(f, v)
+            }
+            SvcParam::Ech(config) => write!(f, "={}", base64(config)),
+            SvcParam::Ipv6Hint(v) => {
+                write!(f, "=")?;
+                join(f, v)
+            }
+            SvcParam::Unknown { value, .. } if value.is_empty() => Ok(()),
+            SvcParam::Unknown { value, .. } => {
+                write!(f, "=\"")?;
+                for &c in value {
+                    if c.is_ascii_graphic() && c != b'"' && c != b'\\' {
+                        write!(f, "{}", c as char)?;
+                    } else {
+                        write!(f, "\\{:03}", c)?;
+                    }
+                }
+                write!(f, "\"")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}.", self.svc_priority, self.target_name)?;
+
+        for param in &self.svc_params {
+            write!(f, " {}", param)?;
+        }
+
+        Ok(())
+    }
+}
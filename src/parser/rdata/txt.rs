@@ -1,14 +1,90 @@
-use crate::{ParserError, Qname};
+use crate::ParserError;
+use std::fmt;
 
 #[derive(Debug)]
 pub struct Record {
-    sets: Vec<u8>,
+    pub strings: Vec<Vec<u8>>,
+}
+
+/// A single DNS-SD `key=value` pair from a TXT record. RFC 6763 Section 6.4 & 6.5
+///
+/// `value` is `None` for boolean attributes (`key` with no `=`) and `Some(&[])` for attributes
+/// with an empty value (`key=`). Values are binary and may not be valid UTF-8.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Attribute<'a> {
+    pub key: &'a str,
+    pub value: Option<&'a [u8]>,
+}
+
+impl Attribute<'_> {
+    pub fn value_str(&self) -> Option<&str> {
+        self.value.and_then(|v| std::str::from_utf8(v).ok())
+    }
 }
 
 impl Record {
-    pub fn parse(data: &[u8], original: &[u8]) -> Result<Self, ParserError> {
-        Ok(Self {
-            sets: data.to_vec(),
+    pub fn parse(mut data: &[u8], _original: &[u8]) -> Result<Self, ParserError> {
+        let mut strings = vec![];
+
+        while !data.is_empty() {
+            let length = data[0] as usize;
+            if 1 + length > data.len() {
+                return Err(ParserError::UnexpectedEOP);
+            }
+
+            strings.push(data[1..1 + length].to_vec());
+            data = &data[1 + length..];
+        }
+
+        Ok(Self { strings })
+    }
+
+    /// DNS-SD key/value view of the record.
+    /// Strings with an empty or non UTF-8 key are silently ignored as required by the RFC.
+    pub fn attributes(&self) -> impl Iterator<Item = Attribute<'_>> {
+        self.strings.iter().filter_map(|s| {
+            let (key, value) = match s.iter().position(|&c| c == b'=') {
+                Some(p) => (&s[..p], Some(&s[p + 1..])),
+                None => (&s[..], None),
+            };
+
+            match std::str::from_utf8(key) {
+                Ok(key) if !key.is_empty() => Some(Attribute { key, value }),
+                _ => None,
+            }
         })
     }
+
+    /// Keys are case insensitive and only the first occurrence of a key is considered.
+    pub fn get(&self, key: &str) -> Option<Attribute<'_>> {
+        self.attributes().find(|a| a.key.eq_ignore_ascii_case(key))
+    }
+}
+
+/// Writes a `<character-string>` in quoted presentation format. RFC 1035 Section 5.1
+pub(crate) fn write_character_string(f: &mut fmt::Formatter<'_>, s: &[u8]) -> fmt::Result {
+    write!(f, "\"")?;
+
+    for &c in s {
+        match c {
+            b'"' | b'\\' => write!(f, "\\{}", c as char)?,
+            c if c.is_ascii_graphic() || c == b' ' => write!(f, "{}", c as char)?,
+            c => write!(f, "\\{:03}", c)?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, s) in self.strings.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write_character_string(f, s)?;
+        }
+
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use crate::{ParserError, Qname, RData};
+use std::fmt;
 
 #[derive(Debug)]
 pub struct ResourceRecord {
@@ -21,8 +22,11 @@ impl ResourceRecord {
         let rtype = Type::parse(u16::from_be_bytes([data[read], data[read + 1]]))?;
         read += 2;
 
-        let (cache_flush, class) =
-            Self::parse_class(u16::from_be_bytes([data[read], data[read + 1]]));
+        let raw_class = u16::from_be_bytes([data[read], data[read + 1]]);
+        let (cache_flush, class) = match rtype {
+            Type::Opt => (false, raw_class),
+            _ => Self::parse_class(raw_class),
+        };
         read += 2;
 
         let ttl = u32::from_be_bytes([data[read], data[read + 1], data[read + 2], data[read + 3]]);
@@ -34,7 +38,10 @@ impl ResourceRecord {
             return Err(ParserError::UnexpectedEOP);
         }
 
-        let rdata = RData::parse(rtype, &data[read..read + rdlength as usize], original)?;
+        let rdata = match rtype {
+            Type::Opt => RData::parse_opt(class, ttl, &data[read..read + rdlength as usize])?,
+            _ => RData::parse(rtype, &data[read..read + rdlength as usize], original)?,
+        };
         read += rdlength as usize;
 
         Ok((
@@ -60,30 +67,105 @@ impl ResourceRecord {
 #[non_exhaustive]
 pub enum Type {
     A = 1,
+    Ns = 2,
     Cname = 5,
+    Soa = 6,
     Ptr = 12,
+    Hinfo = 13,
+    Mx = 15,
     Txt = 16,
     Aaaa = 28,
     Srv = 33,
+    Dname = 39,
     Nsec = 47,
+    Svcb = 64,
     Https = 65,
     Opt = 41,
 }
 
 impl Type {
-    fn parse(v: u16) -> Result<Self, ParserError> {
+    pub(crate) fn parse(v: u16) -> Result<Self, ParserError> {
         use Type::*;
         match v {
             1 => Ok(A),
+            2 => Ok(Ns),
             5 => Ok(Cname),
+            6 => Ok(Soa),
             12 => Ok(Ptr),
+            13 => Ok(Hinfo),
+            15 => Ok(Mx),
             16 => Ok(Txt),
             28 => Ok(Aaaa),
             33 => Ok(Srv),
+            39 => Ok(Dname),
             47 => Ok(Nsec),
+            64 => Ok(Svcb),
             65 => Ok(Https),
             41 => Ok(Opt),
             v => Err(ParserError::UnknownRType(v)),
         }
     }
 }
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Type::A => "A",
+            Type::Ns => "NS",
+            Type::Cname => "CNAME",
+            Type::Soa => "SOA",
+            Type::Ptr => "PTR",
+            Type::Hinfo => "HINFO",
+            Type::Mx => "MX",
+            Type::Txt => "TXT",
+            Type::Aaaa => "AAAA",
+            Type::Srv => "SRV",
+            Type::Dname => "DNAME",
+            Type::Nsec => "NSEC",
+            Type::Svcb => "SVCB",
+            Type::Https => "HTTPS",
+            Type::Opt => "OPT",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Mnemonic for a raw TYPE value, falling back to the RFC 3597 `TYPEnn` form
+pub(crate) fn type_name(v: u16) -> String {
+    match (v, Type::parse(v)) {
+        (_, Ok(t)) => t.to_string(),
+        (255, _) => "ANY".to_string(),
+        (v, _) => format!("TYPE{}", v),
+    }
+}
+
+/// Mnemonic for a raw CLASS value, falling back to the RFC 3597 `CLASSnn` form
+pub(crate) fn class_name(v: u16) -> String {
+    match v {
+        1 => "IN".to_string(),
+        3 => "CH".to_string(),
+        4 => "HS".to_string(),
+        255 => "ANY".to_string(),
+        v => format!("CLASS{}", v),
+    }
+}
+
+/// Zone file presentation format, e.g. `printer.local. 120 IN A 10.0.0.5`
+impl fmt::Display for ResourceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let RData::Opt(_) = self.rdata {
+            return write!(f, "{}", self.rdata);
+        }
+
+        write!(
+            f,
+            "{}. {} {} {} {}",
+            self.name,
+            self.ttl,
+            class_name(self.class),
+            self.rtype,
+            self.rdata
+        )
+    }
+}
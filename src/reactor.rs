@@ -0,0 +1,203 @@
+// A small epoll based event loop. Every protocol module registers its sockets here along with
+// any timers it needs so all of them can be served from a single thread without polling.
+use log::{trace, warn};
+use nix::{
+    errno::Errno,
+    sys::{
+        epoll::{self, EpollCreateFlags, EpollEvent, EpollFlags, EpollOp},
+        signal::{SigSet, Signal},
+        signalfd::{SfdFlags, SignalFd},
+        time::TimeSpec,
+        timerfd::{ClockId, Expiration, TimerFd, TimerFlags, TimerSetTimeFlags},
+    },
+    unistd,
+};
+use std::{
+    collections::HashMap,
+    io::{self, Result as IoResult},
+    os::unix::io::{AsRawFd, RawFd},
+    time::Duration,
+};
+
+pub type Token = u64;
+
+/// Passed to every handler so it can stop the event loop
+#[derive(Debug, Default)]
+pub struct Control {
+    stop: bool,
+}
+
+impl Control {
+    pub fn stop(&mut self) {
+        self.stop = true;
+    }
+}
+
+type Handler = Box<dyn FnMut(&mut Control)>;
+type SignalHandler = Box<dyn FnMut(Signal, &mut Control)>;
+
+enum Source {
+    Fd(RawFd, Handler),
+    Timer(TimerFd, Handler),
+    Signal(SignalFd, SignalHandler),
+}
+
+impl Source {
+    fn raw_fd(&self) -> RawFd {
+        match self {
+            Source::Fd(fd, _) => *fd,
+            Source::Timer(timer, _) => timer.as_raw_fd(),
+            Source::Signal(sfd, _) => sfd.as_raw_fd(),
+        }
+    }
+}
+
+pub struct Reactor {
+    epoll_fd: RawFd,
+    sources: HashMap<Token, Source>,
+    next_token: Token,
+}
+
+impl Reactor {
+    const MAX_EVENTS: usize = 32;
+
+    pub fn new() -> IoResult<Self> {
+        let epoll_fd =
+            epoll::epoll_create1(EpollCreateFlags::EPOLL_CLOEXEC).map_err(io::Error::from)?;
+
+        Ok(Self {
+            epoll_fd,
+            sources: HashMap::new(),
+            next_token: 0,
+        })
+    }
+
+    /// Calls `handler` whenever `fd` is readable. The fd should be non blocking and the handler
+    /// is expected to read from it until it returns `EAGAIN`.
+    pub fn register<F>(&mut self, fd: RawFd, handler: F) -> IoResult<Token>
+    where
+        F: FnMut(&mut Control) + 'static,
+    {
+        self.add(Source::Fd(fd, Box::new(handler)))
+    }
+
+    /// Calls `handler` every `interval`
+    pub fn add_timer<F>(&mut self, interval: Duration, handler: F) -> IoResult<Token>
+    where
+        F: FnMut(&mut Control) + 'static,
+    {
+        let timer = TimerFd::new(
+            ClockId::CLOCK_MONOTONIC,
+            TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC,
+        )
+        .map_err(io::Error::from)?;
+
+        timer
+            .set(
+                Expiration::Interval(TimeSpec::from(interval)),
+                TimerSetTimeFlags::empty(),
+            )
+            .map_err(io::Error::from)?;
+
+        self.add(Source::Timer(timer, Box::new(handler)))
+    }
+
+    /// Blocks `signals` for the process and delivers them to `handler` instead. This should be
+    /// called before any threads are spawned so they inherit the signal mask.
+    pub fn add_signals<F>(&mut self, signals: &[Signal], handler: F) -> IoResult<Token>
+    where
+        F: FnMut(Signal, &mut Control) + 'static,
+    {
+        let mut mask = SigSet::empty();
+        for signal in signals {
+            mask.add(*signal);
+        }
+
+        mask.thread_block().map_err(io::Error::from)?;
+
+        let sfd = SignalFd::with_flags(&mask, SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC)
+            .map_err(io::Error::from)?;
+
+        self.add(Source::Signal(sfd, Box::new(handler)))
+    }
+
+    fn add(&mut self, source: Source) -> IoResult<Token> {
+        let token = self.next_token;
+        self.next_token += 1;
+
+        let mut event = EpollEvent::new(EpollFlags::EPOLLIN, token);
+        epoll::epoll_ctl(
+            self.epoll_fd,
+            EpollOp::EpollCtlAdd,
+            source.raw_fd(),
+            &mut event,
+        )
+        .map_err(io::Error::from)?;
+
+        self.sources.insert(token, source);
+
+        Ok(token)
+    }
+
+    /// Dispatches events until a handler calls `Control::stop`
+    pub fn run(&mut self) -> IoResult<()> {
+        let mut events = vec![EpollEvent::empty(); Self::MAX_EVENTS];
+        let mut control = Control::default();
+
+        while !control.stop {
+            let n = match epoll::epoll_wait(self.epoll_fd, &mut events, -1) {
+                Ok(n) => n,
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(io::Error::from(e)),
+            };
+
+            for event in &events[..n] {
+                let token = event.data();
+                let Some(source) = self.sources.get_mut(&token) else {
+                    continue;
+                };
+
+                trace!("event on token {}", token);
+
+                match source {
+                    Source::Fd(_, handler) => handler(&mut control),
+
+                    Source::Timer(timer, handler) => {
+                        // Reset the expiration count, we only care that the timer fired
+                        let mut buf = [0; 8];
+                        if let Err(e) = unistd::read(timer.as_raw_fd(), &mut buf) {
+                            if e != Errno::EAGAIN {
+                                warn!("error in reading from timer: {}", e);
+                            }
+                            continue;
+                        }
+
+                        handler(&mut control);
+                    }
+
+                    Source::Signal(sfd, handler) => loop {
+                        match sfd.read_signal() {
+                            Ok(Some(info)) => match Signal::try_from(info.ssi_signo as i32) {
+                                Ok(signal) => handler(signal, &mut control),
+                                Err(e) => warn!("unknown signal {}: {}", info.ssi_signo, e),
+                            },
+                            Ok(None) => break,
+                            Err(e) => {
+                                warn!("error in reading from signalfd: {}", e);
+                                break;
+                            }
+                        }
+                    },
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Reactor {
+    fn drop(&mut self) {
+        let _ = unistd::close(self.epoll_fd);
+    }
+}
@@ -0,0 +1,260 @@
+// Batched receive and transmit with `recvmmsg`/`sendmmsg`. Checkout the [recvmmsg
+// manpage](https://man7.org/linux/man-pages/man2/recvmmsg.2.html) for more details. libc is
+// used directly since nix shares one set of control messages across every message of a batch,
+// and each transmitted copy needs its own IP_PKTINFO.
+use super::{Interface, Message};
+use log::debug;
+use std::{
+    io::{self, Result as IoResult},
+    mem,
+    net::{Ipv4Addr, SocketAddrV4},
+    os::unix::io::RawFd,
+    ptr,
+};
+
+/// Maximum number of messages read or written by one system call
+pub(super) const BATCH_SIZE: usize = 32;
+
+fn pktinfo_space() -> usize {
+    unsafe { libc::CMSG_SPACE(mem::size_of::<libc::in_pktinfo>() as u32) as usize }
+}
+
+/// Room for the IP_PKTINFO and IP_TTL control messages of a received packet
+fn receive_control_space() -> usize {
+    pktinfo_space() + unsafe { libc::CMSG_SPACE(mem::size_of::<libc::c_int>() as u32) as usize }
+}
+
+/// Zeroed buffer for control messages. `cmsghdr` has to be aligned, so it is backed by `u64`s.
+fn control_buffer(len: usize) -> Vec<u64> {
+    vec![0; len.div_ceil(mem::size_of::<u64>())]
+}
+
+/// Data and control buffers for `recvmmsg` that are reused between calls
+#[derive(Debug)]
+pub(super) struct ReceiveBuffers {
+    data: Vec<Vec<u8>>,
+    control: Vec<Vec<u64>>,
+}
+
+impl ReceiveBuffers {
+    pub fn new(buffer_size: usize) -> Self {
+        Self {
+            data: vec![vec![0; buffer_size]; BATCH_SIZE],
+            control: vec![control_buffer(receive_control_space()); BATCH_SIZE],
+        }
+    }
+}
+
+/// Reads up to `max` messages, capped at `BATCH_SIZE`, with their IP_PKTINFO and IP_TTL in a
+/// single call. Only waits for the first message. Datagrams that didn't fit the buffer are
+/// dropped, so fewer messages than were read may be returned.
+pub(super) fn receive(
+    fd: RawFd,
+    buffers: &mut ReceiveBuffers,
+    max: usize,
+) -> IoResult<Vec<Message>> {
+    let max = max.clamp(1, BATCH_SIZE);
+    let mut addresses: Vec<libc::sockaddr_in> = vec![unsafe { mem::zeroed() }; max];
+
+    let mut iovecs: Vec<libc::iovec> = buffers
+        .data
+        .iter_mut()
+        .map(|buf| libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        })
+        .collect();
+
+    let mut headers: Vec<libc::mmsghdr> = (0..max)
+        .map(|i| {
+            let mut header: libc::mmsghdr = unsafe { mem::zeroed() };
+            header.msg_hdr.msg_name = &mut addresses[i] as *mut _ as *mut libc::c_void;
+            header.msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+            header.msg_hdr.msg_iov = &mut iovecs[i];
+            header.msg_hdr.msg_iovlen = 1;
+            header.msg_hdr.msg_control = buffers.control[i].as_mut_ptr() as *mut libc::c_void;
+            header.msg_hdr.msg_controllen = receive_control_space() as _;
+            header
+        })
+        .collect();
+
+    let count = unsafe {
+        libc::recvmmsg(
+            fd,
+            headers.as_mut_ptr(),
+            max as libc::c_uint,
+            libc::MSG_WAITFORONE,
+            ptr::null_mut(),
+        )
+    };
+
+    if count < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let messages = headers[..count as usize]
+        .iter()
+        .enumerate()
+        .filter_map(|(i, header)| {
+            if header.msg_hdr.msg_flags & libc::MSG_TRUNC != 0 {
+                debug!(
+                    "dropping datagram larger than the {} byte receive buffer",
+                    buffers.data[i].len()
+                );
+                return None;
+            }
+
+            let origin_address = (header.msg_hdr.msg_namelen as usize
+                >= mem::size_of::<libc::sockaddr_in>())
+            .then(|| {
+                SocketAddrV4::new(
+                    Ipv4Addr::from(u32::from_be(addresses[i].sin_addr.s_addr)),
+                    u16::from_be(addresses[i].sin_port),
+                )
+                .into()
+            });
+
+            let (interface, ttl) = control_messages(&header.msg_hdr);
+
+            Some(Message {
+                data: buffers.data[i][..header.msg_len as usize].to_vec(),
+                origin_address,
+                interface,
+                ttl,
+                source_mac: None,
+            })
+        })
+        .collect();
+
+    Ok(messages)
+}
+
+/// Returns the ingress interface and the IP TTL of a received packet
+fn control_messages(header: &libc::msghdr) -> (Interface, Option<u8>) {
+    let mut interface = Interface::Default;
+    let mut ttl = None;
+
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(header);
+        while !cmsg.is_null() {
+            match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+                (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                    let pktinfo: libc::in_pktinfo =
+                        ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::in_pktinfo);
+                    interface = Interface::Index(pktinfo.ipi_ifindex as _);
+                }
+                (libc::IPPROTO_IP, libc::IP_TTL) => {
+                    let value: libc::c_int =
+                        ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+                    ttl = Some(value as u8);
+                }
+                _ => {}
+            }
+
+            cmsg = libc::CMSG_NXTHDR(header, cmsg);
+        }
+    }
+
+    (interface, ttl)
+}
+
+/// Sends `buf` once for every entry in `pktinfos`. The returned results are in the same order
+/// as `pktinfos`. A message that fails is reported and skipped, the rest are still sent.
+pub(super) fn send(
+    fd: RawFd,
+    buf: &[u8],
+    destination: SocketAddrV4,
+    pktinfos: &[libc::in_pktinfo],
+) -> Vec<IoResult<usize>> {
+    let mut address: libc::sockaddr_in = unsafe { mem::zeroed() };
+    address.sin_family = libc::AF_INET as libc::sa_family_t;
+    address.sin_port = destination.port().to_be();
+    address.sin_addr.s_addr = u32::from(*destination.ip()).to_be();
+
+    let mut iovec = libc::iovec {
+        iov_base: buf.as_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+
+    let space = pktinfo_space();
+    let mut control: Vec<Vec<u64>> = vec![control_buffer(space); pktinfos.len()];
+
+    let mut headers: Vec<libc::mmsghdr> = pktinfos
+        .iter()
+        .enumerate()
+        .map(|(i, pktinfo)| {
+            let mut header: libc::mmsghdr = unsafe { mem::zeroed() };
+            header.msg_hdr.msg_name = &mut address as *mut _ as *mut libc::c_void;
+            header.msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+            header.msg_hdr.msg_iov = &mut iovec;
+            header.msg_hdr.msg_iovlen = 1;
+            header.msg_hdr.msg_control = control[i].as_mut_ptr() as *mut libc::c_void;
+            header.msg_hdr.msg_controllen = space as _;
+
+            unsafe {
+                let cmsg = libc::CMSG_FIRSTHDR(&header.msg_hdr);
+                (*cmsg).cmsg_level = libc::IPPROTO_IP;
+                (*cmsg).cmsg_type = libc::IP_PKTINFO;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<libc::in_pktinfo>() as u32) as _;
+                ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::in_pktinfo, *pktinfo);
+            }
+
+            header
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(pktinfos.len());
+
+    while results.len() < headers.len() {
+        let remaining = &mut headers[results.len()..];
+        let count = unsafe {
+            libc::sendmmsg(
+                fd,
+                remaining.as_mut_ptr(),
+                remaining.len().min(BATCH_SIZE) as libc::c_uint,
+                0,
+            )
+        };
+
+        if count < 0 {
+            // The first message of the remaining batch failed
+            results.push(Err(io::Error::last_os_error()));
+            continue;
+        }
+
+        for header in &remaining[..count as usize] {
+            results.push(Ok(header.msg_len as usize));
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{net::UdpSocket, os::unix::io::AsRawFd};
+
+    #[test]
+    fn receive_without_filling_the_batch() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender
+            .send_to(&[0; 100], receiver.local_addr().unwrap())
+            .unwrap();
+        sender
+            .send_to(b"ping", receiver.local_addr().unwrap())
+            .unwrap();
+
+        // The socket is blocking, so this only returns if it doesn't wait for a full batch
+        let mut buffers = ReceiveBuffers::new(16);
+        let messages = receive(receiver.as_raw_fd(), &mut buffers, BATCH_SIZE).unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].data, b"ping");
+        assert_eq!(
+            messages[0].origin_address,
+            Some(sender.local_addr().unwrap())
+        );
+    }
+}
@@ -0,0 +1,200 @@
+// One socket per interface, each bound to its interface with SO_BINDTODEVICE. Checkout the
+// [socket manpage](https://man7.org/linux/man-pages/man7/socket.7.html) for more details. The
+// sockets are kept in a private epoll instance so the whole set can be polled through one fd.
+use super::{
+    batch::{self, ReceiveBuffers},
+    nix_to_io_error, set_ttl_options, Interface, Message, MulticastOptions,
+};
+use crate::interfaces::if_indextoname;
+use log::trace;
+use nix::{
+    errno::Errno,
+    sys::epoll::{self, EpollCreateFlags, EpollEvent, EpollFlags, EpollOp},
+    unistd,
+};
+use socket2::{Domain, InterfaceIndexOrAddress, Protocol, Socket, Type};
+use std::{
+    collections::HashMap,
+    io::{self, Result as IoResult},
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
+    os::unix::io::{AsRawFd, RawFd},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+
+#[derive(Debug)]
+pub(super) struct DeviceSockets {
+    epoll_fd: RawFd,
+    sockets: RwLock<HashMap<u32, Socket>>,
+    multicast_group: SocketAddrV4,
+    nonblocking: AtomicBool,
+    read_timeout: Option<Duration>,
+    multicast_ttl: Option<u8>,
+}
+
+impl DeviceSockets {
+    pub fn new(multicast_group: SocketAddrV4, options: &MulticastOptions) -> IoResult<Self> {
+        let epoll_fd =
+            epoll::epoll_create1(EpollCreateFlags::EPOLL_CLOEXEC).map_err(nix_to_io_error)?;
+
+        Ok(Self {
+            epoll_fd,
+            sockets: RwLock::new(HashMap::new()),
+            multicast_group,
+            nonblocking: AtomicBool::new(options.nonblocking),
+            read_timeout: options.read_timeout,
+            multicast_ttl: options.multicast_ttl,
+        })
+    }
+
+    /// Opens a socket bound to the interface and joins the multicast group on it. Adding an
+    /// interface twice is not an error.
+    pub fn add(&self, index: u32) -> IoResult<()> {
+        let mut sockets = self.sockets.write().unwrap();
+        if sockets.contains_key(&index) {
+            return Ok(());
+        }
+
+        let name = if_indextoname(index).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no interface {}", index))
+        })?;
+
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_nonblocking(true)?;
+        socket.set_multicast_loop_v4(false)?;
+        socket.set_reuse_address(true)?;
+        socket.set_reuse_port(true)?;
+        socket.bind_device(Some(name.as_bytes()))?;
+        set_ttl_options(&socket, self.multicast_ttl)?;
+        socket.bind(
+            &SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                self.multicast_group.port(),
+            )
+            .into(),
+        )?;
+        socket.join_multicast_v4_n(
+            self.multicast_group.ip(),
+            &InterfaceIndexOrAddress::Index(index),
+        )?;
+
+        let mut event = EpollEvent::new(EpollFlags::EPOLLIN, index as u64);
+        epoll::epoll_ctl(
+            self.epoll_fd,
+            EpollOp::EpollCtlAdd,
+            socket.as_raw_fd(),
+            &mut event,
+        )
+        .map_err(nix_to_io_error)?;
+
+        trace!("bound socket to {} ({})", name, index);
+
+        sockets.insert(index, socket);
+
+        Ok(())
+    }
+
+    /// Closes the socket of the interface, which also leaves the multicast group
+    pub fn remove(&self, index: u32) -> IoResult<()> {
+        if let Some(socket) = self.sockets.write().unwrap().remove(&index) {
+            epoll::epoll_ctl(
+                self.epoll_fd,
+                EpollOp::EpollCtlDel,
+                socket.as_raw_fd(),
+                None,
+            )
+            .map_err(nix_to_io_error)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+
+    /// Receives up to `max` packets from one of the interfaces. The ingress interface is the one
+    /// the socket is bound to, so IP_PKTINFO is not needed.
+    pub fn receive(&self, buffers: &mut ReceiveBuffers, max: usize) -> IoResult<Vec<Message>> {
+        let nonblocking = self.nonblocking.load(Ordering::Relaxed);
+        let timeout = match (nonblocking, self.read_timeout) {
+            (true, _) => 0,
+            (false, Some(timeout)) => timeout.as_millis() as isize,
+            (false, None) => -1,
+        };
+
+        loop {
+            let mut events = [EpollEvent::empty()];
+            let n = match epoll::epoll_wait(self.epoll_fd, &mut events, timeout) {
+                Ok(n) => n,
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(nix_to_io_error(e)),
+            };
+
+            if n == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+
+            let index = events[0].data() as u32;
+            let sockets = self.sockets.read().unwrap();
+            let Some(socket) = sockets.get(&index) else {
+                continue;
+            };
+
+            match batch::receive(socket.as_raw_fd(), buffers, max) {
+                Ok(mut messages) => {
+                    for message in &mut messages {
+                        message.interface = Interface::Index(index as i32);
+                    }
+
+                    return Ok(messages);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && !nonblocking => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn send(&self, buf: &[u8], index: u32, pktinfo: libc::in_pktinfo) -> IoResult<usize> {
+        let sockets = self.sockets.read().unwrap();
+        let socket = sockets.get(&index).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no socket bound to interface {}", index),
+            )
+        })?;
+
+        batch::send(socket.as_raw_fd(), buf, self.multicast_group, &[pktinfo]).remove(0)
+    }
+
+    /// Socket bound to the interface. Unlike the epoll fd it can be polled for writability.
+    #[cfg(feature = "tokio")]
+    pub fn socket_fd(&self, index: u32) -> IoResult<RawFd> {
+        self.sockets
+            .read()
+            .unwrap()
+            .get(&index)
+            .map(|socket| socket.as_raw_fd())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no socket bound to interface {}", index),
+                )
+            })
+    }
+}
+
+impl AsRawFd for DeviceSockets {
+    fn as_raw_fd(&self) -> RawFd {
+        self.epoll_fd
+    }
+}
+
+impl Drop for DeviceSockets {
+    fn drop(&mut self) {
+        let _ = unistd::close(self.epoll_fd);
+    }
+}
@@ -0,0 +1,663 @@
+use log::{trace, warn};
+// This code has been adapted from multicast_socket crate
+use nix::sys::socket::{self as sock, SockaddrIn};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, InterfaceIndexOrAddress, Protocol, Socket, Type};
+use std::{
+    collections::HashMap,
+    io::{self, IoSlice, Result as IoResult},
+    mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    os::unix::io::{AsRawFd, RawFd},
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::Duration,
+};
+
+mod batch;
+mod device;
+mod packet;
+
+use batch::ReceiveBuffers;
+use device::DeviceSockets;
+use packet::PacketSocket;
+
+/// How packets are received and sent on the configured interfaces
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// A single socket bound to 0.0.0.0 that uses IP_PKTINFO to find the ingress interface
+    #[default]
+    Wildcard,
+    /// One socket per interface bound with SO_BINDTODEVICE. This coexists better with other
+    /// listeners on the same port and doesn't depend on IP_PKTINFO.
+    BindToDevice,
+    /// Raw AF_PACKET capture and injection. The port is never bound, so this can run next to
+    /// avahi-daemon. Packets sent by other programs on this host are not captured.
+    Packet,
+}
+
+pub struct MulticastOptions {
+    pub read_timeout: Option<Duration>,
+    pub nonblocking: bool,
+    pub buffer_size: usize,
+    pub backend: Backend,
+    /// TTL of sent packets. The kernel default of 1 is used if this is `None`.
+    pub multicast_ttl: Option<u8>,
+    /// Group that packets sent to an `Interface::IpAddr(IpAddr::V6(..))` are addressed to
+    pub multicast_group_v6: Option<SocketAddrV6>,
+}
+
+impl Default for MulticastOptions {
+    fn default() -> Self {
+        MulticastOptions {
+            read_timeout: None,
+            nonblocking: false,
+            buffer_size: 4096,
+            backend: Backend::default(),
+            multicast_ttl: None,
+            multicast_group_v6: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Inner {
+    Wildcard(Socket),
+    Device(Arc<DeviceSockets>),
+    Packet(Arc<PacketSocket>),
+}
+
+#[derive(Debug)]
+pub struct MulticastSocket {
+    inner: Inner,
+    multicast_group: SocketAddrV4,
+    buffer_size: usize,
+    receive_buffers: Mutex<ReceiveBuffers>,
+    /// Source address to use for packets sent out of each interface
+    source_addresses: Arc<RwLock<HashMap<u32, Ipv4Addr>>>,
+    multicast_ttl: Option<u8>,
+    multicast_group_v6: Option<SocketAddrV6>,
+    /// Unbound IPv4 socket for backends that can only send out of an interface index. The
+    /// kernel picks the egress interface from the routing table.
+    route_socket: OnceLock<Socket>,
+    /// Unbound IPv6 socket, created on the first IPv6 send
+    socket_v6: OnceLock<Socket>,
+}
+
+impl MulticastSocket {
+    pub fn new(
+        options: MulticastOptions,
+        interfaces: HashMap<String, Vec<IpAddr>>,
+        multicast_group: SocketAddrV4,
+    ) -> Result<Self, std::io::Error> {
+        let inner = match options.backend {
+            Backend::Wildcard => Inner::Wildcard(Self::wildcard_socket(
+                &options,
+                &interfaces,
+                multicast_group,
+            )?),
+            Backend::BindToDevice => {
+                let devices = DeviceSockets::new(multicast_group, &options)?;
+
+                for index in Self::ipv4_interfaces(&interfaces)? {
+                    devices.add(index)?;
+                }
+
+                Inner::Device(Arc::new(devices))
+            }
+            Backend::Packet => {
+                let packet = PacketSocket::new(multicast_group, &options)?;
+
+                // L3 interfaces like WireGuard or tun have no hardware address to capture on.
+                // They are skipped rather than keeping the other interfaces from working.
+                for index in Self::ipv4_interfaces(&interfaces)? {
+                    if let Err(e) = packet.add(index) {
+                        warn!("not capturing on interface {}: {}", index, e);
+                    }
+                }
+
+                Inner::Packet(Arc::new(packet))
+            }
+        };
+
+        Ok(MulticastSocket {
+            inner,
+            buffer_size: options.buffer_size,
+            multicast_group,
+            receive_buffers: Mutex::new(ReceiveBuffers::new(options.buffer_size)),
+            source_addresses: Arc::default(),
+            multicast_ttl: options.multicast_ttl,
+            multicast_group_v6: options.multicast_group_v6,
+            route_socket: OnceLock::new(),
+            socket_v6: OnceLock::new(),
+        })
+    }
+
+    /// Indexes of the interfaces that have a non loopback IPv4 address
+    fn ipv4_interfaces(interfaces: &HashMap<String, Vec<IpAddr>>) -> IoResult<Vec<u32>> {
+        interfaces
+            .iter()
+            .filter(|(_, addresses)| addresses.iter().any(|a| a.is_ipv4() && !a.is_loopback()))
+            .map(|(if_name, _)| {
+                nix::net::if_::if_nametoindex(if_name.as_str()).map_err(nix_to_io_error)
+            })
+            .collect()
+    }
+
+    fn wildcard_socket(
+        options: &MulticastOptions,
+        interfaces: &HashMap<String, Vec<IpAddr>>,
+        multicast_group: SocketAddrV4,
+    ) -> IoResult<Socket> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_read_timeout(options.read_timeout)?;
+        socket.set_nonblocking(options.nonblocking)?;
+        socket.set_multicast_loop_v4(false)?;
+        socket.set_reuse_address(true)?;
+        socket.set_reuse_port(true)?;
+
+        // Ipv4PacketInfo translates to `IP_PKTINFO`. Checkout the [ip
+        // manpage](https://man7.org/linux/man-pages/man7/ip.7.html) for more details. In summary
+        // setting this option allows for determining on which interface a packet was received.
+        sock::setsockopt(socket.as_raw_fd(), sock::sockopt::Ipv4PacketInfo, &true)
+            .map_err(nix_to_io_error)?;
+        set_ttl_options(&socket, options.multicast_ttl)?;
+
+        // Joining by address fails with EADDRINUSE on the second address of an interface, so
+        // the group is joined once per interface index
+        for index in Self::ipv4_interfaces(interfaces)? {
+            match socket
+                .join_multicast_v4_n(multicast_group.ip(), &InterfaceIndexOrAddress::Index(index))
+            {
+                Err(e) if e.kind() == io::ErrorKind::AddrInUse => {}
+                result => result?,
+            }
+
+            trace!(
+                "joined ipv4 multicast group {} on {}",
+                multicast_group.ip(),
+                index
+            );
+        }
+
+        socket.bind(
+            &SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), multicast_group.port()).into(),
+        )?;
+
+        Ok(socket)
+    }
+
+    /// Returns a new handle to the same underlying socket
+    pub fn try_clone(&self) -> IoResult<Self> {
+        let inner = match &self.inner {
+            Inner::Wildcard(socket) => Inner::Wildcard(socket.try_clone()?),
+            Inner::Device(devices) => Inner::Device(Arc::clone(devices)),
+            Inner::Packet(packet) => Inner::Packet(Arc::clone(packet)),
+        };
+
+        Ok(MulticastSocket {
+            inner,
+            multicast_group: self.multicast_group,
+            buffer_size: self.buffer_size,
+            receive_buffers: Mutex::new(ReceiveBuffers::new(self.buffer_size)),
+            source_addresses: Arc::clone(&self.source_addresses),
+            multicast_ttl: self.multicast_ttl,
+            multicast_group_v6: self.multicast_group_v6,
+            route_socket: OnceLock::new(),
+            socket_v6: OnceLock::new(),
+        })
+    }
+
+    /// Joins the multicast group on the interface with the given index. Joining an interface
+    /// that is already a member is not an error.
+    pub fn join_interface(&self, index: u32) -> IoResult<()> {
+        let socket = match &self.inner {
+            Inner::Wildcard(socket) => socket,
+            Inner::Device(devices) => return devices.add(index),
+            Inner::Packet(packet) => return packet.add(index),
+        };
+
+        match socket.join_multicast_v4_n(
+            self.multicast_group.ip(),
+            &InterfaceIndexOrAddress::Index(index),
+        ) {
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Leaves the multicast group on the interface with the given index
+    pub fn leave_interface(&self, index: u32) -> IoResult<()> {
+        match &self.inner {
+            Inner::Wildcard(socket) => socket.leave_multicast_v4_n(
+                self.multicast_group.ip(),
+                &InterfaceIndexOrAddress::Index(index),
+            ),
+            Inner::Device(devices) => devices.remove(index),
+            Inner::Packet(packet) => packet.remove(index),
+        }
+    }
+
+    /// Sets the address packets sent out of the interface should use as their source. Without
+    /// one the kernel picks an address, which may not be the primary one.
+    pub fn set_source_address(&self, index: u32, address: Option<Ipv4Addr>) {
+        let mut source_addresses = self.source_addresses.write().unwrap();
+
+        match address {
+            Some(address) => {
+                source_addresses.insert(index, address);
+                if let Inner::Packet(packet) = &self.inner {
+                    packet.set_source_address(index, address);
+                }
+            }
+            None => {
+                source_addresses.remove(&index);
+            }
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> IoResult<()> {
+        match &self.inner {
+            Inner::Wildcard(socket) => socket.set_nonblocking(nonblocking),
+            Inner::Device(devices) => {
+                devices.set_nonblocking(nonblocking);
+                Ok(())
+            }
+            Inner::Packet(packet) => packet.set_nonblocking(nonblocking),
+        }
+    }
+
+    pub fn all_interfaces() -> IoResult<HashMap<String, Vec<IpAddr>>> {
+        let interfaces = get_if_addrs::get_if_addrs()?.into_iter();
+        // We have to filter the same interface if it has multiple ips
+        // https://stackoverflow.com/questions/49819010/ip-add-membership-fails-when-set-both-on-interface-and-its-subinterface-is-that
+        let mut map = HashMap::new();
+
+        for interface in interfaces {
+            map.entry(interface.name.clone())
+                .or_insert_with(Vec::new)
+                .push(interface.ip());
+        }
+
+        // TODO: remove loopback?
+        map.remove("lo");
+
+        Ok(map)
+    }
+}
+
+impl AsRawFd for MulticastSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        match &self.inner {
+            Inner::Wildcard(socket) => socket.as_raw_fd(),
+            Inner::Device(devices) => devices.as_raw_fd(),
+            Inner::Packet(packet) => packet.as_raw_fd(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub data: Vec<u8>,
+    /// Only the packet backend receives over IPv6
+    pub origin_address: Option<SocketAddr>,
+    pub interface: Interface,
+    /// IP TTL the packet was received with, if the backend reports it
+    pub ttl: Option<u8>,
+    /// Ethernet source address. Only the packet backend sees the link layer header.
+    pub source_mac: Option<[u8; 6]>,
+}
+
+impl Message {
+    /// Index of the interface the packet arrived on. Packets received without IP_PKTINFO don't
+    /// say where they came from and are rejected.
+    pub fn interface_index(&self) -> IoResult<u32> {
+        match self.interface {
+            Interface::Index(index) => Ok(index as u32),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "packet was received without its ingress interface",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Interface {
+    /// Let the routing table pick the egress interface
+    Default,
+    Index(i32),
+    /// The interface that has this address. IPv6 addresses are sent from an IPv6 socket to
+    /// `MulticastOptions::multicast_group_v6`.
+    IpAddr(IpAddr),
+}
+
+impl Interface {
+    fn is_ipv6(&self) -> bool {
+        matches!(self, Interface::IpAddr(IpAddr::V6(_)))
+    }
+}
+
+fn nix_to_io_error(e: nix::Error) -> io::Error {
+    io::Error::from(e)
+}
+
+/// Sets the TTL of sent multicast packets and asks for the TTL of received ones with `IP_RECVTTL`
+fn set_ttl_options(socket: &Socket, multicast_ttl: Option<u8>) -> IoResult<()> {
+    if let Some(ttl) = multicast_ttl {
+        socket.set_multicast_ttl_v4(ttl as u32)?;
+    }
+
+    let enable: libc::c_int = 1;
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_RECVTTL,
+            &enable as *const _ as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+impl MulticastSocket {
+    pub fn receive(&self) -> IoResult<Message> {
+        let mut buffers = self.receive_buffers.lock().unwrap();
+
+        let messages = match &self.inner {
+            Inner::Wildcard(socket) => batch::receive(socket.as_raw_fd(), &mut buffers, 1)?,
+            Inner::Device(devices) => devices.receive(&mut buffers, 1)?,
+            Inner::Packet(packet) => return packet.receive(self.buffer_size),
+        };
+
+        messages.into_iter().next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "received a datagram larger than the receive buffer",
+            )
+        })
+    }
+
+    pub fn send(&self, buf: &[u8], interface: &Interface) -> io::Result<usize> {
+        let socket = match (&self.inner, interface) {
+            (_, Interface::IpAddr(IpAddr::V6(address))) => return self.send_v6(buf, address),
+            (Inner::Device(devices), Interface::Index(i)) => {
+                return devices.send(buf, *i as u32, self.pktinfo(interface))
+            }
+            (Inner::Packet(packet), Interface::Index(i)) => return packet.send(buf, *i as u32),
+            (Inner::Wildcard(socket), _) => socket,
+            (_, _) => self.route_socket()?,
+        };
+
+        sock::sendmsg(
+            socket.as_raw_fd(),
+            &[IoSlice::new(buf)],
+            &[sock::ControlMessage::Ipv4PacketInfo(
+                &self.pktinfo(interface),
+            )],
+            sock::MsgFlags::empty(),
+            Some(&SockaddrIn::from(self.multicast_group)),
+        )
+        .map_err(nix_to_io_error)
+    }
+
+    /// Sends out of the interface that has `address`, using it as the source address
+    fn send_v6(&self, buf: &[u8], address: &Ipv6Addr) -> IoResult<usize> {
+        let group = self.multicast_group_v6.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "no IPv6 multicast group is configured",
+            )
+        })?;
+
+        // Looked up on every send, this path is rare and the addresses may have changed
+        let index = get_if_addrs::get_if_addrs()?
+            .into_iter()
+            .find(|i| i.ip() == IpAddr::V6(*address))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::AddrNotAvailable,
+                    format!("no interface has address {}", address),
+                )
+            })
+            .and_then(|i| {
+                nix::net::if_::if_nametoindex(i.name.as_str()).map_err(nix_to_io_error)
+            })?;
+
+        let pktinfo = libc::in6_pktinfo {
+            ipi6_addr: libc::in6_addr {
+                s6_addr: address.octets(),
+            },
+            ipi6_ifindex: index,
+        };
+
+        sock::sendmsg(
+            self.socket_v6()?.as_raw_fd(),
+            &[IoSlice::new(buf)],
+            &[sock::ControlMessage::Ipv6PacketInfo(&pktinfo)],
+            sock::MsgFlags::empty(),
+            Some(&sock::SockaddrIn6::from(group)),
+        )
+        .map_err(nix_to_io_error)
+    }
+
+    /// Socket that `send` uses for `interface`
+    #[cfg(feature = "tokio")]
+    fn send_fd(&self, interface: &Interface) -> IoResult<RawFd> {
+        match (&self.inner, interface) {
+            (_, Interface::IpAddr(IpAddr::V6(_))) => Ok(self.socket_v6()?.as_raw_fd()),
+            (Inner::Device(devices), Interface::Index(i)) => devices.socket_fd(*i as u32),
+            (Inner::Packet(packet), Interface::Index(_)) => Ok(packet.as_raw_fd()),
+            (Inner::Wildcard(socket), _) => Ok(socket.as_raw_fd()),
+            (_, _) => Ok(self.route_socket()?.as_raw_fd()),
+        }
+    }
+
+    fn route_socket(&self) -> IoResult<&Socket> {
+        if let Some(socket) = self.route_socket.get() {
+            return Ok(socket);
+        }
+
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_multicast_loop_v4(false)?;
+        set_ttl_options(&socket, self.multicast_ttl)?;
+
+        Ok(self.route_socket.get_or_init(|| socket))
+    }
+
+    fn socket_v6(&self) -> IoResult<&Socket> {
+        if let Some(socket) = self.socket_v6.get() {
+            return Ok(socket);
+        }
+
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_only_v6(true)?;
+        socket.set_multicast_loop_v6(false)?;
+        if let Some(ttl) = self.multicast_ttl {
+            socket.set_multicast_hops_v6(ttl as u32)?;
+        }
+
+        Ok(self.socket_v6.get_or_init(|| socket))
+    }
+
+    /// Receives every packet that is queued, up to a batch, with a single `recvmmsg`. The
+    /// receive buffers are reused between calls.
+    pub fn receive_batch(&self) -> IoResult<Vec<Message>> {
+        match &self.inner {
+            Inner::Wildcard(socket) => batch::receive(
+                socket.as_raw_fd(),
+                &mut self.receive_buffers.lock().unwrap(),
+                batch::BATCH_SIZE,
+            ),
+            Inner::Device(devices) => {
+                devices.receive(&mut self.receive_buffers.lock().unwrap(), batch::BATCH_SIZE)
+            }
+            Inner::Packet(_) => self.receive().map(|msg| vec![msg]),
+        }
+    }
+
+    /// Sends the same packet out of each of `interfaces` with a single `sendmmsg`. There is one
+    /// result per interface, in the same order.
+    pub fn send_many(&self, buf: &[u8], interfaces: &[Interface]) -> Vec<IoResult<usize>> {
+        match &self.inner {
+            Inner::Wildcard(socket) if !interfaces.iter().any(Interface::is_ipv6) => {
+                let pktinfos: Vec<_> = interfaces.iter().map(|i| self.pktinfo(i)).collect();
+                batch::send(socket.as_raw_fd(), buf, self.multicast_group, &pktinfos)
+            }
+            _ => interfaces
+                .iter()
+                .map(|interface| self.send(buf, interface))
+                .collect(),
+        }
+    }
+
+    /// `IP_PKTINFO` for a packet sent out of `interface`. `ipi_spec_dst` sets the source
+    /// address, so the interface's primary address is used when one is known. Leaving both
+    /// fields zeroed lets the routing table decide. IPv6 addresses use `send_v6` instead.
+    fn pktinfo(&self, interface: &Interface) -> libc::in_pktinfo {
+        let mut pkt_info: libc::in_pktinfo = unsafe { mem::zeroed() };
+
+        match interface {
+            Interface::Default | Interface::IpAddr(IpAddr::V6(_)) => {}
+            Interface::Index(i) => {
+                pkt_info.ipi_ifindex = *i as _;
+
+                if let Some(source) = self.source_addresses.read().unwrap().get(&(*i as u32)) {
+                    pkt_info.ipi_spec_dst = libc::in_addr {
+                        s_addr: u32::from(*source).to_be(),
+                    };
+                }
+            }
+            Interface::IpAddr(IpAddr::V4(addr)) => {
+                pkt_info.ipi_spec_dst = libc::in_addr {
+                    s_addr: u32::from(*addr).to_be(),
+                };
+            }
+        }
+
+        pkt_info
+    }
+}
+
+/// Async version of `MulticastSocket` for use with a tokio runtime. It uses the same
+/// `recvmsg`/`sendmsg` calls so the ingress interface is still available on every `Message`.
+#[cfg(feature = "tokio")]
+pub struct AsyncMulticastSocket {
+    inner: tokio::io::unix::AsyncFd<MulticastSocket>,
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncMulticastSocket {
+    /// Must be called from within a tokio runtime
+    pub fn new(socket: MulticastSocket) -> IoResult<Self> {
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            inner: tokio::io::unix::AsyncFd::new(socket)?,
+        })
+    }
+
+    pub fn get_ref(&self) -> &MulticastSocket {
+        self.inner.get_ref()
+    }
+
+    pub fn into_inner(self) -> MulticastSocket {
+        self.inner.into_inner()
+    }
+
+    pub async fn receive(&self) -> IoResult<Message> {
+        loop {
+            let mut guard = self.inner.readable().await?;
+
+            match guard.try_io(|inner| inner.get_ref().receive()) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    pub async fn receive_batch(&self) -> IoResult<Vec<Message>> {
+        loop {
+            let mut guard = self.inner.readable().await?;
+
+            match guard.try_io(|inner| inner.get_ref().receive_batch()) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Sends right away and only waits for the socket to have room if the send would block.
+    /// The fd registered with tokio isn't always the one that sends, the BindToDevice backend
+    /// registers an epoll fd that never becomes writable.
+    pub async fn send(&self, buf: &[u8], interface: &Interface) -> IoResult<usize> {
+        loop {
+            match self.get_ref().send(buf, interface) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => self.writable(interface).await?,
+                result => return result,
+            }
+        }
+    }
+
+    /// Waits until the socket that sends out of `interface` is writable
+    async fn writable(&self, interface: &Interface) -> IoResult<()> {
+        let fd = self.get_ref().send_fd(interface)?;
+
+        // An fd can only be registered with tokio once
+        if fd == self.inner.as_raw_fd() {
+            self.inner.writable().await?.clear_ready();
+        } else {
+            tokio::io::unix::AsyncFd::with_interest(fd, tokio::io::Interest::WRITABLE)?
+                .writable()
+                .await?
+                .clear_ready();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn async_send_on_bind_to_device() {
+        let group = SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+        let socket = MulticastSocket::new(
+            MulticastOptions {
+                backend: Backend::BindToDevice,
+                ..Default::default()
+            },
+            HashMap::new(),
+            group,
+        )
+        .expect("error in creating socket");
+
+        let lo = nix::net::if_::if_nametoindex("lo").expect("error in finding lo");
+        socket.join_interface(lo).expect("error in joining lo");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let socket = AsyncMulticastSocket::new(socket).unwrap();
+            let sent = tokio::time::timeout(
+                Duration::from_secs(2),
+                socket.send(b"hello", &Interface::Index(lo as i32)),
+            )
+            .await
+            .expect("send did not finish");
+
+            assert_eq!(sent.unwrap(), 5);
+        });
+    }
+}
@@ -0,0 +1,535 @@
+// Raw AF_PACKET capture and injection. Checkout the [packet
+// manpage](https://man7.org/linux/man-pages/man7/packet.7.html) for more details. Frames are
+// picked up with a BPF filter instead of binding the UDP port, so this works alongside other
+// listeners like avahi-daemon. Packets to the IPv6 group are captured too, so rules see IPv6
+// senders. Forwarded packets are written as complete Ethernet frames over IPv4.
+use super::{nix_to_io_error, Interface, Message, MulticastOptions};
+use crate::interfaces::if_indextoname;
+use log::trace;
+use nix::{
+    ifaddrs,
+    sys::socket::{self as sock, LinkAddr},
+};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    collections::HashMap,
+    io::{self, Result as IoResult},
+    mem,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    os::unix::io::{AsRawFd, RawFd},
+    sync::RwLock,
+};
+
+// Not exported by libc. Frames sent by this host, including our own, are seen with this type.
+const PACKET_OUTGOING: u8 = 4;
+
+const ETH_HEADER_LEN: usize = 14;
+const IP_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const UDP_HEADER_LEN: usize = 8;
+
+/// Used when no TTL is configured. RFC 6762 Section 11
+const DEFAULT_TTL: u8 = 255;
+
+#[derive(Debug)]
+struct Link {
+    mac: [u8; 6],
+    address: Ipv4Addr,
+}
+
+#[derive(Debug)]
+pub(super) struct PacketSocket {
+    socket: Socket,
+    links: RwLock<HashMap<u32, Link>>,
+    multicast_group: SocketAddrV4,
+    /// Also captured if set, but never sent to
+    multicast_group_v6: Option<SocketAddrV6>,
+    ttl: u8,
+}
+
+impl PacketSocket {
+    pub fn new(multicast_group: SocketAddrV4, options: &MulticastOptions) -> IoResult<Self> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be() as i32;
+
+        let socket = Socket::new(Domain::PACKET, Type::RAW, Some(Protocol::from(protocol)))?;
+        socket.set_nonblocking(options.nonblocking)?;
+        socket.set_read_timeout(options.read_timeout)?;
+        socket.attach_filter(&filter(multicast_group, options.multicast_group_v6))?;
+
+        Ok(Self {
+            socket,
+            links: RwLock::new(HashMap::new()),
+            multicast_group,
+            multicast_group_v6: options.multicast_group_v6,
+            ttl: options.multicast_ttl.unwrap_or(DEFAULT_TTL),
+        })
+    }
+
+    /// Starts accepting frames from the interface. The multicast MAC addresses of the groups are
+    /// added to the interface so the NIC doesn't drop the frames.
+    pub fn add(&self, index: u32) -> IoResult<()> {
+        let mut links = self.links.write().unwrap();
+        if links.contains_key(&index) {
+            return Ok(());
+        }
+
+        let link = Self::link(index)?;
+        self.membership(index, libc::PACKET_ADD_MEMBERSHIP)?;
+
+        trace!("capturing on {} {:?}", index, link);
+
+        links.insert(index, link);
+
+        Ok(())
+    }
+
+    pub fn remove(&self, index: u32) -> IoResult<()> {
+        if self.links.write().unwrap().remove(&index).is_some() {
+            self.membership(index, libc::PACKET_DROP_MEMBERSHIP)?;
+        }
+
+        Ok(())
+    }
+
+    /// Changes the source address used in frames sent out of the interface
+    pub fn set_source_address(&self, index: u32, address: Ipv4Addr) {
+        if let Some(link) = self.links.write().unwrap().get_mut(&index) {
+            link.address = address;
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> IoResult<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    /// Looks up the hardware address and the first IPv4 address of an interface
+    fn link(index: u32) -> IoResult<Link> {
+        let name = if_indextoname(index).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no interface {}", index))
+        })?;
+
+        let mut mac = None;
+        let mut address = None;
+
+        for ifaddr in ifaddrs::getifaddrs().map_err(nix_to_io_error)? {
+            if ifaddr.interface_name != name {
+                continue;
+            }
+
+            let Some(addr) = ifaddr.address else {
+                continue;
+            };
+
+            if let Some(link) = addr.as_link_addr() {
+                mac = mac.or(link.addr());
+            } else if let Some(v4) = addr.as_sockaddr_in() {
+                address = address.or(Some(Ipv4Addr::from_bits(v4.ip())));
+            }
+        }
+
+        match (mac, address) {
+            (Some(mac), Some(address)) => Ok(Link { mac, address }),
+            (None, _) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} has no hardware address", name),
+            )),
+            (_, None) => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} has no ipv4 address", name),
+            )),
+        }
+    }
+
+    fn membership(&self, index: u32, option: libc::c_int) -> IoResult<()> {
+        let macs = std::iter::once(multicast_mac(*self.multicast_group.ip())).chain(
+            self.multicast_group_v6
+                .map(|group| multicast_mac_v6(*group.ip())),
+        );
+
+        for mac in macs {
+            let mut mreq: libc::packet_mreq = unsafe { mem::zeroed() };
+            mreq.mr_ifindex = index as _;
+            mreq.mr_type = libc::PACKET_MR_MULTICAST as _;
+            mreq.mr_alen = 6;
+            mreq.mr_address[..6].copy_from_slice(&mac);
+
+            let ret = unsafe {
+                libc::setsockopt(
+                    self.socket.as_raw_fd(),
+                    libc::SOL_PACKET,
+                    option,
+                    &mreq as *const _ as *const libc::c_void,
+                    mem::size_of::<libc::packet_mreq>() as libc::socklen_t,
+                )
+            };
+
+            if ret < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Receives the UDP payload of the next frame that arrived on one of the added interfaces
+    pub fn receive(&self, buffer_size: usize) -> IoResult<Message> {
+        let mut frame = vec![0; buffer_size + ETH_HEADER_LEN + IPV6_HEADER_LEN + UDP_HEADER_LEN];
+
+        loop {
+            let (bytes_read, addr) =
+                sock::recvfrom::<LinkAddr>(self.socket.as_raw_fd(), &mut frame)
+                    .map_err(nix_to_io_error)?;
+
+            let Some(addr) = addr else {
+                continue;
+            };
+
+            let index = addr.ifindex() as u32;
+            if addr.pkttype() == PACKET_OUTGOING || !self.links.read().unwrap().contains_key(&index)
+            {
+                continue;
+            }
+
+            let Some((origin_address, ttl, data)) = parse_frame(&frame[..bytes_read]) else {
+                trace!("dropping malformed frame from {}", index);
+                continue;
+            };
+
+            return Ok(Message {
+                data: data.to_vec(),
+                origin_address: Some(origin_address),
+                interface: Interface::Index(index as i32),
+                ttl: Some(ttl),
+                source_mac: frame[6..12].try_into().ok(),
+            });
+        }
+    }
+
+    /// Sends `buf` as a UDP packet to the multicast group out of the interface. The frame uses
+    /// the interface's own MAC and IPv4 address as the source.
+    pub fn send(&self, buf: &[u8], index: u32) -> IoResult<usize> {
+        let links = self.links.read().unwrap();
+        let link = links.get(&index).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("not capturing on interface {}", index),
+            )
+        })?;
+
+        let frame = build_frame(link, self.multicast_group, self.ttl, buf)?;
+        let dst_mac = multicast_mac(*self.multicast_group.ip());
+
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as _;
+        addr.sll_protocol = (libc::ETH_P_IP as u16).to_be();
+        addr.sll_ifindex = index as _;
+        addr.sll_halen = 6;
+        addr.sll_addr[..6].copy_from_slice(&dst_mac);
+
+        let ret = unsafe {
+            libc::sendto(
+                self.socket.as_raw_fd(),
+                frame.as_ptr() as *const libc::c_void,
+                frame.len(),
+                0,
+                &addr as *const _ as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(buf.len())
+    }
+}
+
+impl AsRawFd for PacketSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+/// Classic BPF program equivalent to `tcpdump -dd 'ip dst <group> and udp dst port <port>'`
+/// with fragments rejected. With an IPv6 group, `ip6 dst <group> and udp dst port <port>` is
+/// accepted too, as long as UDP directly follows the fixed header.
+fn filter(group: SocketAddrV4, group_v6: Option<SocketAddrV6>) -> Vec<libc::sock_filter> {
+    // Jumps are relative, so the ones to the final `ret #0` are filled in at the end
+    const REJECT: u8 = u8::MAX;
+    let op = |code: u16, jt: u8, jf: u8, k: u32| libc::sock_filter { code, jt, jf, k };
+
+    let mut program = vec![
+        op(0x28, 0, 0, 12),                            // ldh [12]
+        op(0x15, 0, 10, libc::ETH_P_IP as u32),        // jeq #0x800, else to the IPv6 part
+        op(0x20, 0, 0, 30),                            // ld [30]
+        op(0x15, 0, REJECT, u32::from(*group.ip())),   // jeq #group
+        op(0x30, 0, 0, 23),                            // ldb [23]
+        op(0x15, 0, REJECT, libc::IPPROTO_UDP as u32), // jeq #17
+        op(0x28, 0, 0, 20),                            // ldh [20]
+        op(0x45, REJECT, 0, 0x1fff),                   // jset #0x1fff
+        op(0xb1, 0, 0, 14),                            // ldxb 4*([14]&0xf)
+        op(0x48, 0, 0, 16),                            // ldh [x + 16]
+        op(0x15, 0, REJECT, group.port() as u32),      // jeq #port
+        op(0x06, 0, 0, 0x40000),                       // ret #262144
+    ];
+
+    if let Some(group_v6) = group_v6 {
+        let word =
+            |i: usize| u32::from_be_bytes(group_v6.ip().octets()[i..i + 4].try_into().unwrap());
+
+        program.extend([
+            op(0x15, 0, REJECT, libc::ETH_P_IPV6 as u32), // jeq #0x86dd
+            op(0x30, 0, 0, 20),                           // ldb [20]
+            op(0x15, 0, REJECT, libc::IPPROTO_UDP as u32), // jeq #17
+            op(0x20, 0, 0, 38),                           // ld [38]
+            op(0x15, 0, REJECT, word(0)),                 // jeq #group[0..4]
+            op(0x20, 0, 0, 42),                           // ld [42]
+            op(0x15, 0, REJECT, word(4)),                 // jeq #group[4..8]
+            op(0x20, 0, 0, 46),                           // ld [46]
+            op(0x15, 0, REJECT, word(8)),                 // jeq #group[8..12]
+            op(0x20, 0, 0, 50),                           // ld [50]
+            op(0x15, 0, REJECT, word(12)),                // jeq #group[12..16]
+            op(0x28, 0, 0, 56),                           // ldh [56]
+            op(0x15, 0, REJECT, group_v6.port() as u32),  // jeq #port
+            op(0x06, 0, 0, 0x40000),                      // ret #262144
+        ]);
+    }
+
+    program.push(op(0x06, 0, 0, 0)); // ret #0
+
+    let reject = program.len() - 1;
+    for (i, instruction) in program.iter_mut().enumerate() {
+        if instruction.jt == REJECT {
+            instruction.jt = (reject - i - 1) as u8;
+        }
+        if instruction.jf == REJECT {
+            instruction.jf = (reject - i - 1) as u8;
+        }
+    }
+
+    program
+}
+
+/// Ethernet address of an IPv4 multicast group. RFC 1112 Section 6.4
+fn multicast_mac(group: Ipv4Addr) -> [u8; 6] {
+    let o = group.octets();
+    [0x01, 0x00, 0x5e, o[1] & 0x7f, o[2], o[3]]
+}
+
+/// Ethernet address of an IPv6 multicast group. RFC 2464 Section 7
+fn multicast_mac_v6(group: Ipv6Addr) -> [u8; 6] {
+    let o = group.octets();
+    [0x33, 0x33, o[12], o[13], o[14], o[15]]
+}
+
+/// Returns the source address, IP TTL or hop limit and UDP payload of an Ethernet frame
+fn parse_frame(frame: &[u8]) -> Option<(SocketAddr, u8, &[u8])> {
+    let ethertype = u16::from_be_bytes(frame.get(12..ETH_HEADER_LEN)?.try_into().ok()?);
+    let ip = &frame[ETH_HEADER_LEN..];
+
+    match ethertype as libc::c_int {
+        libc::ETH_P_IP => parse_ipv4(ip),
+        libc::ETH_P_IPV6 => parse_ipv6(ip),
+        _ => None,
+    }
+}
+
+fn parse_ipv4(ip: &[u8]) -> Option<(SocketAddr, u8, &[u8])> {
+    if ip.len() < IP_HEADER_LEN || ip[0] >> 4 != 4 {
+        return None;
+    }
+
+    let ihl = ((ip[0] & 0xf) as usize) * 4;
+    let total_len = u16::from_be_bytes([ip[2], ip[3]]) as usize;
+    if ihl < IP_HEADER_LEN || total_len > ip.len() || ihl > total_len {
+        return None;
+    }
+
+    let src = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]);
+    let (src_port, payload) = parse_udp(&ip[ihl..total_len])?;
+
+    Some((SocketAddrV4::new(src, src_port).into(), ip[8], payload))
+}
+
+/// Packets with extension headers are rejected by the filter and here
+fn parse_ipv6(ip: &[u8]) -> Option<(SocketAddr, u8, &[u8])> {
+    if ip.len() < IPV6_HEADER_LEN || ip[0] >> 4 != 6 || ip[6] != libc::IPPROTO_UDP as u8 {
+        return None;
+    }
+
+    let payload_len = u16::from_be_bytes([ip[4], ip[5]]) as usize;
+    let src: [u8; 16] = ip[8..24].try_into().ok()?;
+    let (src_port, payload) = parse_udp(ip.get(IPV6_HEADER_LEN..IPV6_HEADER_LEN + payload_len)?)?;
+
+    Some((
+        SocketAddrV6::new(Ipv6Addr::from(src), src_port, 0, 0).into(),
+        ip[7],
+        payload,
+    ))
+}
+
+/// Returns the source port and payload of a UDP datagram
+fn parse_udp(udp: &[u8]) -> Option<(u16, &[u8])> {
+    if udp.len() < UDP_HEADER_LEN {
+        return None;
+    }
+
+    let udp_len = u16::from_be_bytes([udp[4], udp[5]]) as usize;
+    if udp_len < UDP_HEADER_LEN || udp_len > udp.len() {
+        return None;
+    }
+
+    Some((
+        u16::from_be_bytes([udp[0], udp[1]]),
+        &udp[UDP_HEADER_LEN..udp_len],
+    ))
+}
+
+fn build_frame(link: &Link, group: SocketAddrV4, ttl: u8, payload: &[u8]) -> IoResult<Vec<u8>> {
+    let udp_len = UDP_HEADER_LEN + payload.len();
+    let total_len = IP_HEADER_LEN + udp_len;
+    if total_len > u16::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "payload does not fit in an ipv4 packet",
+        ));
+    }
+
+    let src = link.address.octets();
+    let dst = group.ip().octets();
+
+    let mut frame = Vec::with_capacity(ETH_HEADER_LEN + total_len);
+
+    // Ethernet
+    frame.extend_from_slice(&multicast_mac(*group.ip()));
+    frame.extend_from_slice(&link.mac);
+    frame.extend_from_slice(&(libc::ETH_P_IP as u16).to_be_bytes());
+
+    // IPv4, RFC 791 Section 3.1
+    let ip_start = frame.len();
+    frame.extend_from_slice(&[0x45, 0]);
+    frame.extend_from_slice(&(total_len as u16).to_be_bytes());
+    frame.extend_from_slice(&[0, 0, 0, 0, ttl, libc::IPPROTO_UDP as u8, 0, 0]);
+    frame.extend_from_slice(&src);
+    frame.extend_from_slice(&dst);
+
+    let ip_checksum = checksum(&[&frame[ip_start..]]);
+    frame[ip_start + 10..ip_start + 12].copy_from_slice(&ip_checksum.to_be_bytes());
+
+    // UDP, RFC 768
+    let udp_start = frame.len();
+    frame.extend_from_slice(&group.port().to_be_bytes());
+    frame.extend_from_slice(&group.port().to_be_bytes());
+    frame.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(payload);
+
+    let mut pseudo_header = [0; 12];
+    pseudo_header[0..4].copy_from_slice(&src);
+    pseudo_header[4..8].copy_from_slice(&dst);
+    pseudo_header[9] = libc::IPPROTO_UDP as u8;
+    pseudo_header[10..12].copy_from_slice(&(udp_len as u16).to_be_bytes());
+
+    // A checksum of 0 means no checksum was computed, so it is sent as all ones
+    let udp_checksum = match checksum(&[&pseudo_header, &frame[udp_start..]]) {
+        0 => 0xffff,
+        sum => sum,
+    };
+    frame[udp_start + 6..udp_start + 8].copy_from_slice(&udp_checksum.to_be_bytes());
+
+    Ok(frame)
+}
+
+/// Internet checksum over the concatenation of `parts`. RFC 1071
+fn checksum(parts: &[&[u8]]) -> u16 {
+    let mut sum = 0u32;
+    let mut odd = None;
+
+    for byte in parts.iter().flat_map(|p| p.iter()) {
+        match odd.take() {
+            Some(high) => sum += u16::from_be_bytes([high, *byte]) as u32,
+            None => odd = Some(*byte),
+        }
+    }
+
+    if let Some(high) = odd {
+        sum += u16::from_be_bytes([high, 0]) as u32;
+    }
+
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_and_parse_frame() {
+        let link = Link {
+            mac: [0x02, 0, 0, 0, 0, 1],
+            address: Ipv4Addr::new(192, 168, 1, 10),
+        };
+        let group = SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+
+        let frame = build_frame(&link, group, DEFAULT_TTL, b"hello").unwrap();
+
+        assert_eq!(&frame[0..6], &[0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb]);
+        assert_eq!(&frame[6..12], &link.mac);
+        assert_eq!(frame[ETH_HEADER_LEN + 8], 255);
+
+        // Summing a header including its own checksum gives zero
+        assert_eq!(checksum(&[&frame[ETH_HEADER_LEN..ETH_HEADER_LEN + 20]]), 0);
+
+        let ip = &frame[ETH_HEADER_LEN..];
+        let mut pseudo_header = vec![];
+        pseudo_header.extend_from_slice(&ip[12..20]);
+        pseudo_header.extend_from_slice(&[0, 17, 0, 13]);
+        assert_eq!(checksum(&[&pseudo_header, &ip[20..]]), 0);
+
+        assert_eq!(
+            parse_frame(&frame),
+            Some((
+                SocketAddrV4::new(link.address, 5353).into(),
+                255,
+                &b"hello"[..]
+            ))
+        );
+        assert_eq!(parse_frame(&frame[..30]), None);
+    }
+
+    #[test]
+    fn parse_ipv6_frame() {
+        let src = "fe80::1".parse::<Ipv6Addr>().unwrap();
+        let group = "ff02::fb".parse::<Ipv6Addr>().unwrap();
+
+        let mut frame = vec![];
+        frame.extend_from_slice(&multicast_mac_v6(group));
+        frame.extend_from_slice(&[0x02, 0, 0, 0, 0, 1]);
+        frame.extend_from_slice(&(libc::ETH_P_IPV6 as u16).to_be_bytes());
+        frame.extend_from_slice(&[0x60, 0, 0, 0, 0, 13, libc::IPPROTO_UDP as u8, 255]);
+        frame.extend_from_slice(&src.octets());
+        frame.extend_from_slice(&group.octets());
+        frame.extend_from_slice(&[0x14, 0xe9, 0x14, 0xe9, 0, 13, 0, 0]);
+        frame.extend_from_slice(b"hello");
+
+        assert_eq!(&frame[0..6], &[0x33, 0x33, 0x00, 0x00, 0x00, 0xfb]);
+        assert_eq!(
+            parse_frame(&frame),
+            Some((
+                SocketAddrV6::new(src, 5353, 0, 0).into(),
+                255,
+                &b"hello"[..]
+            ))
+        );
+        assert_eq!(parse_frame(&frame[..frame.len() - 1]), None);
+
+        // A hop-by-hop options header in front of UDP
+        frame[ETH_HEADER_LEN + 6] = 0;
+        assert_eq!(parse_frame(&frame), None);
+    }
+}